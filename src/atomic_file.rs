@@ -0,0 +1,67 @@
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, remove_file, rename};
+use tokio::io::AsyncWriteExt;
+use tracing::trace;
+
+pub(crate) const TEMP_FILE_EXTENSION: &str = "tmp";
+
+/// Write a file atomically.
+///
+/// - Contents are written to a temporary sibling file which is synced to disk
+/// - The temporary file is renamed over the target
+/// - The parent directory is synced so the rename survives a crash
+///
+/// Readers observe either the previous or the new contents, never a partial write.
+pub(crate) async fn write_atomic(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let temp_path = get_temp_path(path);
+    let result = write_and_rename(path, &temp_path, contents.as_ref()).await;
+    if result.is_err() {
+        let _ = remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// Get the path of the temporary sibling file used by [`write_atomic`].
+pub(crate) fn get_temp_path(path: &Path) -> PathBuf {
+    let mut file_name: OsString = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".");
+    file_name.push(TEMP_FILE_EXTENSION);
+    path.with_file_name(file_name)
+}
+
+async fn write_and_rename(path: &Path, temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(temp_path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+    rename(temp_path, path).await?;
+    sync_parent_dir(path).await?;
+    trace!(path = %path.display(), "Atomic write complete");
+    Ok(())
+}
+
+/// Sync the parent directory so a rename within it is durable.
+#[cfg(unix)]
+pub(crate) async fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent).await?.sync_all().await
+}
+
+/// Directories can't be opened for syncing on this platform.
+#[cfg(not(unix))]
+#[expect(
+    clippy::unused_async,
+    reason = "signature must match the unix implementation"
+)]
+pub(crate) async fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
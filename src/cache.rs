@@ -0,0 +1,241 @@
+use crate::sidecar::ChunkStamp;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::trace;
+
+/// Counters of a [`Table`] chunk cache.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Number of chunk reads served from the cache.
+    pub hits: u64,
+    /// Number of chunk reads that read the chunk file.
+    pub misses: u64,
+    /// Number of chunks currently cached.
+    pub chunks: usize,
+}
+
+/// Size-bounded least recently used cache of deserialized chunks.
+///
+/// Clones share the same cache.
+pub(crate) struct ChunkCache<const K: usize, const C: usize, T> {
+    state: Arc<Mutex<CacheState<K, C, T>>>,
+}
+
+struct CacheState<const K: usize, const C: usize, T> {
+    capacity: usize,
+    entries: BTreeMap<Hash<C>, CacheEntry<K, T>>,
+    /// Incremented on every access to order entries by last use.
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+struct CacheEntry<const K: usize, T> {
+    fingerprint: Fingerprint,
+    chunk: Arc<BTreeMap<Hash<K>, T>>,
+    last_used: u64,
+}
+
+/// Identity of the files a cached chunk was read from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Fingerprint {
+    chunk: Option<FileFingerprint>,
+    wal: Option<FileFingerprint>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct FileFingerprint {
+    path: PathBuf,
+    stamp: ChunkStamp,
+}
+
+impl<const K: usize, const C: usize, T> ChunkCache<K, C, T> {
+    /// Create an empty cache holding at most `capacity` chunks.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState {
+                capacity,
+                entries: BTreeMap::new(),
+                clock: 0,
+                hits: 0,
+                misses: 0,
+            })),
+        }
+    }
+
+    /// Maximum number of cached chunks.
+    pub(crate) fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    /// Remove a chunk from the cache.
+    pub(crate) fn invalidate(&self, hash: Hash<C>) {
+        if self.lock().entries.remove(&hash).is_some() {
+            trace!(chunk = %hash, "Invalidated cached chunk");
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState<K, C, T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get a chunk if it is cached with the same fingerprint.
+    fn get(&self, hash: Hash<C>, fingerprint: &Fingerprint) -> Option<Arc<BTreeMap<Hash<K>, T>>> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+        let chunk = match state.entries.get_mut(&hash) {
+            Some(entry) if entry.fingerprint == *fingerprint => {
+                entry.last_used = clock;
+                Some(entry.chunk.clone())
+            }
+            _ => None,
+        };
+        if chunk.is_some() {
+            state.hits += 1;
+        } else {
+            state.misses += 1;
+        }
+        chunk
+    }
+
+    /// Add a chunk, evicting the least recently used chunk if the cache is full.
+    fn insert(&self, hash: Hash<C>, fingerprint: Fingerprint, chunk: Arc<BTreeMap<Hash<K>, T>>) {
+        let mut state = self.lock();
+        if state.capacity == 0 {
+            return;
+        }
+        state.entries.remove(&hash);
+        if state.entries.len() >= state.capacity {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(hash, _)| *hash);
+            if let Some(oldest) = oldest {
+                trace!(chunk = %oldest, "Evicting cached chunk");
+                state.entries.remove(&oldest);
+            }
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.entries.insert(
+            hash,
+            CacheEntry {
+                fingerprint,
+                chunk,
+                last_used,
+            },
+        );
+    }
+
+    fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            hits: state.hits,
+            misses: state.misses,
+            chunks: state.entries.len(),
+        }
+    }
+}
+
+impl<const K: usize, const C: usize, T> Clone for ChunkCache<K, C, T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Cache up to `capacity` deserialized chunks in memory.
+    ///
+    /// - The least recently used chunk is evicted when the cache is full
+    /// - A cached chunk is read again if the length, modification time, inode or
+    ///   change time of its chunk file or the write-ahead log changes
+    /// - Chunks written by this process are removed from the cache
+    ///
+    /// Used by [`Table::get`]. Clones of the table share the cache.
+    #[must_use]
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(ChunkCache::new(capacity));
+        self
+    }
+
+    /// Get the hit and miss counters of the chunk cache.
+    ///
+    /// Returns `None` if the cache is not enabled.
+    #[must_use]
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(ChunkCache::stats)
+    }
+
+    /// Remove a chunk from the cache after it is written.
+    pub(crate) fn invalidate_cached_chunk(&self, hash: Hash<C>) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(hash);
+        }
+    }
+
+    /// Get the fingerprint of the files a chunk is read from.
+    ///
+    /// Returns `None` if the chunk does not exist or the modification time is not
+    /// available.
+    async fn get_fingerprint(&self, hash: Hash<C>) -> Option<Fingerprint> {
+        let chunk = match self.find_chunk_path(hash) {
+            Some(path) => Some(get_file_fingerprint(&path).await?),
+            None => None,
+        };
+        let wal_path = self.get_wal_path();
+        let wal = if self.is_wal_enabled() && wal_path.is_file() {
+            Some(get_file_fingerprint(&wal_path).await?)
+        } else {
+            None
+        };
+        if chunk.is_none() && wal.is_none() {
+            return None;
+        }
+        Some(Fingerprint { chunk, wal })
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Read a chunk by hash using the cache if it is enabled.
+    pub(crate) async fn load_chunk_cached(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<Arc<BTreeMap<Hash<K>, T>>, Failure<TableAction>> {
+        let Some(cache) = &self.cache else {
+            return Ok(Arc::new(self.load_chunk_shared(hash).await?));
+        };
+        let Some(fingerprint) = self.get_fingerprint(hash).await else {
+            return Ok(Arc::new(self.load_chunk_shared(hash).await?));
+        };
+        if let Some(chunk) = cache.get(hash, &fingerprint) {
+            trace!(chunk = %hash, "Cache hit");
+            return Ok(chunk);
+        }
+        trace!(chunk = %hash, "Cache miss");
+        let chunk = Arc::new(self.load_chunk_shared(hash).await?);
+        cache.insert(hash, fingerprint, chunk.clone());
+        Ok(chunk)
+    }
+}
+
+/// Get the fingerprint of a file.
+///
+/// Returns `None` if the file does not exist or the modification time is not
+/// available.
+async fn get_file_fingerprint(path: &Path) -> Option<FileFingerprint> {
+    Some(FileFingerprint {
+        path: path.to_path_buf(),
+        stamp: ChunkStamp::read(path).await?,
+    })
+}
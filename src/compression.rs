@@ -0,0 +1,129 @@
+#[cfg(feature = "gzip")]
+use flate2::Compression as GzipLevel;
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "gzip")]
+use flate2::write::GzEncoder;
+use std::ffi::OsString;
+use std::io;
+#[cfg(feature = "gzip")]
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Compression applied to chunk files.
+///
+/// Compressed chunks have an additional extension, for example `ab.yml.zst`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+    /// Chunks are written without compression.
+    #[default]
+    None,
+    /// Chunks are compressed with zstd.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Chunks are compressed with gzip.
+    #[cfg(feature = "gzip")]
+    Gzip,
+}
+
+impl Compression {
+    /// All compression options enabled by cargo features.
+    pub const ALL: &[Compression] = &[
+        Compression::None,
+        #[cfg(feature = "zstd")]
+        Compression::Zstd,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip,
+    ];
+
+    /// Additional file extension of compressed files without the leading dot.
+    #[must_use]
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Some("zst"),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Some("gz"),
+        }
+    }
+
+    /// Determine the compression of a file from its extension.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path.extension().unwrap_or_default();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|compression| {
+                compression
+                    .extension()
+                    .is_some_and(|compressed| extension == compressed)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Append the compression extension to a path.
+    pub(crate) fn apply_to_path(self, path: PathBuf) -> PathBuf {
+        let Some(extension) = self.extension() else {
+            return path;
+        };
+        let mut path: OsString = path.into_os_string();
+        path.push(".");
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    /// Remove the compression extension from a path.
+    pub(crate) fn strip_from_path(self, path: &Path) -> PathBuf {
+        if self.extension().is_some() {
+            path.with_extension("")
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    /// Compress bytes.
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "gzip")),
+        expect(
+            clippy::unnecessary_wraps,
+            reason = "fallible when a compression feature is enabled"
+        )
+    )]
+    pub(crate) fn compress(self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(bytes.as_slice(), 0),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), GzipLevel::default());
+                encoder.write_all(&bytes)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Decompress bytes.
+    #[cfg_attr(
+        not(any(feature = "zstd", feature = "gzip")),
+        expect(
+            clippy::unnecessary_wraps,
+            reason = "fallible when a compression feature is enabled"
+        )
+    )]
+    pub(crate) fn decompress(self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(bytes),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::decode_all(bytes.as_slice()),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+        }
+    }
+}
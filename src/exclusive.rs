@@ -0,0 +1,75 @@
+use crate::lock_guard::{LockGuard, acquire_exclusive_lock, acquire_writer_lock};
+use crate::{ChunkFormat, Table, TableAction, Yaml};
+use rogue_logging::Failure;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs::create_dir_all;
+use tracing::info;
+
+pub(crate) const TABLE_LOCK_NAME: &str = "table";
+
+/// [`Table`] holding the table-wide lock acquired by [`Table::exclusive`].
+///
+/// Dereferences to the [`Table`]. The lock is released once this and every clone of
+/// the table it dereferences to are dropped.
+pub struct ExclusiveTable<const K: usize, const C: usize, T, F = Yaml> {
+    table: Table<K, C, T, F>,
+}
+
+impl<const K: usize, const C: usize, T, F> Deref for ExclusiveTable<K, C, T, F> {
+    type Target = Table<K, C, T, F>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Stop every writer of the table for maintenance.
+    ///
+    /// - Creates a `table.lock` file in the table directory
+    /// - New writers in any process wait until it is removed
+    /// - Waits for in-flight writers to finish
+    /// - The lock is not broken as stale by other processes on this host while this
+    ///   process is running, and its acquired time is refreshed while held so
+    ///   processes on other hosts don't break it however long maintenance takes
+    ///
+    /// Writes through the returned table are not blocked.
+    ///
+    /// [`Table::reshard`] and [`Table::rebuild_index`] acquire the lock automatically.
+    pub async fn exclusive(&self) -> Result<ExclusiveTable<K, C, T, F>, Failure<TableAction>> {
+        let mut table = self.clone();
+        if self.exclusive_lock.is_none() {
+            create_dir_all(&self.directory)
+                .await
+                .map_err(Failure::wrap_with_path(
+                    TableAction::Exclusive,
+                    &self.directory,
+                ))?;
+            let lock = acquire_exclusive_lock(&self.get_table_lock_path(), &self.lock_options)
+                .await
+                .map_err(Failure::wrap(TableAction::Exclusive))?;
+            info!(path = %self.directory.display(), "Acquired exclusive table lock");
+            table.exclusive_lock = Some(Arc::new(lock));
+        }
+        Ok(ExclusiveTable { table })
+    }
+
+    /// Wait until the table is not locked by [`Table::exclusive`] then register as a
+    /// writer.
+    ///
+    /// Returns `None` if this table holds the exclusive lock.
+    pub(crate) async fn lock_writer(&self) -> Result<Option<LockGuard>, Failure<TableAction>> {
+        if self.exclusive_lock.is_some() {
+            return Ok(None);
+        }
+        let guard = acquire_writer_lock(&self.get_table_lock_path(), &self.lock_options).await?;
+        Ok(Some(guard))
+    }
+
+    /// Get the path the table-wide lock is derived from.
+    fn get_table_lock_path(&self) -> PathBuf {
+        self.directory.join(TABLE_LOCK_NAME)
+    }
+}
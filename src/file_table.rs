@@ -1,10 +1,13 @@
-use crate::Hash;
-use futures::future::join_all;
+use crate::exclusive::TABLE_LOCK_NAME;
+use crate::lock_guard::{acquire_lock, acquire_writer_lock};
+use crate::{DEFAULT_CONCURRENCY, DEFAULT_READ_AHEAD, Hash, LockOptions};
+use futures::future;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use rogue_logging::Failure;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error as ThisError;
-use tokio::fs::{copy, create_dir_all, read_dir};
+use tokio::fs::{copy, create_dir_all};
 use tracing::{debug, trace};
 
 /// File storage table with chunked directories.
@@ -17,6 +20,12 @@ pub struct FileTable<const K: usize, const C: usize> {
     pub(crate) directory: PathBuf,
     /// File extension for stored files.
     pub(crate) extension: String,
+    /// Number of chunk directories read ahead when streaming.
+    pub(crate) read_ahead: usize,
+    /// Maximum number of files copied at once by [`FileTable::set_many`].
+    pub(crate) concurrency: usize,
+    /// Options for acquiring lock files.
+    pub(crate) lock_options: LockOptions,
 }
 
 impl<const K: usize, const C: usize> FileTable<K, C> {
@@ -26,11 +35,30 @@ impl<const K: usize, const C: usize> FileTable<K, C> {
         Self {
             directory: directory.into(),
             extension: extension.into(),
+            read_ahead: DEFAULT_READ_AHEAD,
+            concurrency: DEFAULT_CONCURRENCY,
+            lock_options: LockOptions::default(),
         }
     }
 
+    /// Set the maximum number of files copied at once by [`FileTable::set_many`].
+    ///
+    /// Each copy holds a lock file, the source and the stored file open.
+    ///
+    /// Default: [`DEFAULT_CONCURRENCY`]
+    #[must_use]
+    pub fn with_concurrency(mut self, files: usize) -> Self {
+        self.concurrency = files.max(1);
+        self
+    }
+
+    /// Get the path the table-wide lock is derived from.
+    pub(crate) fn get_table_lock_path(&self) -> PathBuf {
+        self.directory.join(TABLE_LOCK_NAME)
+    }
+
     /// Get the path to the file.
-    fn get_path(&self, hash: Hash<K>) -> PathBuf {
+    pub(crate) fn get_path(&self, hash: Hash<K>) -> PathBuf {
         let chunk_hash: Hash<C> = get_chunk_hash(hash);
         self.directory
             .join(chunk_hash.to_hex())
@@ -54,59 +82,42 @@ impl<const K: usize, const C: usize> FileTable<K, C> {
     ///
     /// Items are unsorted.
     pub async fn get_all(&self) -> Result<BTreeMap<Hash<K>, PathBuf>, Failure<FileTableAction>> {
-        let mut paths = BTreeMap::new();
-        let dir_path = self.directory.clone();
-        let mut parent_dir = read_dir(&self.directory)
-            .await
-            .map_err(Failure::wrap_with_path(FileTableAction::ReadDir, &dir_path))?;
-        while let Some(entry) = parent_dir
-            .next_entry()
-            .await
-            .map_err(Failure::wrap(FileTableAction::ReadEntry))?
-        {
-            let path = entry.path();
-            if !path.is_dir() {
-                trace!("Skipping non-chunk directory: {}", path.display());
-                continue;
-            }
-            let chunk_path = path.clone();
-            let mut chunk_dir = read_dir(&path).await.map_err(Failure::wrap_with_path(
-                FileTableAction::ReadChunkDir,
-                &chunk_path,
-            ))?;
-            while let Some(entry) = chunk_dir
-                .next_entry()
-                .await
-                .map_err(Failure::wrap(FileTableAction::ReadChunkEntry))?
-            {
-                let path = entry.path();
-                let extension = path
-                    .extension()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-                if !path.is_file() || extension != self.extension {
-                    trace!("Skipping non-chunk file: {}", path.display());
-                    continue;
-                }
-                let Some(stem) = path.file_stem() else {
-                    trace!("File does not have a stem: {}", path.display());
-                    continue;
-                };
-                let Ok(hash) = Hash::from_string(stem.to_string_lossy().as_ref()) else {
-                    trace!("File stem is not a hash: {}", path.display());
-                    continue;
-                };
-                paths.insert(hash, path);
-            }
-        }
+        let paths: BTreeMap<Hash<K>, PathBuf> = self.stream().try_collect().await?;
         trace!(count = paths.len(), "Get all files");
         Ok(paths)
     }
+
+    /// Check if a file exists.
+    #[must_use]
+    pub fn contains(&self, hash: Hash<K>) -> bool {
+        let found = self.get_path(hash).is_file();
+        trace!(hash = %hash, found, "Contains file");
+        found
+    }
+
+    /// Count the files.
+    pub async fn len(&self) -> Result<usize, Failure<FileTableAction>> {
+        let count = self
+            .stream()
+            .try_fold(0, |count, _| future::ready(Ok(count + 1)))
+            .await?;
+        trace!(count, "Count files");
+        Ok(count)
+    }
+
+    /// Check if the table has no files.
+    ///
+    /// See [`FileTable::len`].
+    pub async fn is_empty(&self) -> Result<bool, Failure<FileTableAction>> {
+        Ok(self.len().await? == 0)
+    }
 }
 
 impl<const K: usize, const C: usize> FileTable<K, C> {
     /// Copy a file into storage.
+    ///
+    /// - Waits while [`FileTable::reshard`] is moving files
+    /// - The stored file is locked while it is copied
     pub async fn set(
         &self,
         hash: Hash<K>,
@@ -127,6 +138,14 @@ impl<const K: usize, const C: usize> FileTable<K, C> {
                 ))
                 .map_err(Failure::wrap(FileTableAction::Set))?;
         }
+        let _writer = acquire_writer_lock(&self.get_table_lock_path(), &self.lock_options)
+            .await
+            .map_err(Failure::wrap(FileTableAction::AcquireLock))
+            .map_err(Failure::wrap(FileTableAction::Set))?;
+        let _lock = acquire_lock(&stored_path, &self.lock_options)
+            .await
+            .map_err(Failure::wrap(FileTableAction::AcquireLock))
+            .map_err(Failure::wrap(FileTableAction::Set))?;
         debug!(hash = %hash, from = %path.display(), to = %stored_path.display(), "Copying file");
         copy(path, &stored_path)
             .await
@@ -140,18 +159,19 @@ impl<const K: usize, const C: usize> FileTable<K, C> {
 
     /// Copy multiple files into storage.
     ///
-    /// Existing files are replaced.
+    /// - Existing files are replaced
+    /// - At most [`FileTable::with_concurrency`] files are copied at once
     pub async fn set_many(
         &self,
         items: BTreeMap<Hash<K>, PathBuf>,
     ) -> Result<(), Failure<FileTableAction>> {
         let count = items.len();
         trace!(count, "Set many files");
-        let tasks: Vec<_> = items
-            .into_iter()
+        let results: Vec<_> = stream::iter(items)
             .map(|(hash, path)| self.set(hash, path))
-            .collect();
-        let results = join_all(tasks).await;
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
         let (successes, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
         if errors.is_empty() {
             trace!(succeeded = count, "Set many files complete");
@@ -197,6 +217,14 @@ pub enum FileTableAction {
     CreateDir,
     #[error("copy file")]
     CopyFile,
+    #[error("move file")]
+    MoveFile,
     #[error("set files")]
     SetMany,
+    #[error("reshard file table")]
+    Reshard,
+    #[error("find by abbreviated hash")]
+    FindByAbbrev,
+    #[error("acquire lock")]
+    AcquireLock,
 }
@@ -0,0 +1,41 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// CBOR chunk format.
+///
+/// Compact self-describing binary format.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Cbor;
+
+impl ChunkFormat for Cbor {
+    fn extension(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(chunk, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(ciborium::from_reader(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![ciborium::from_reader(bytes)?])
+    }
+}
@@ -0,0 +1,46 @@
+#[cfg(feature = "cbor")]
+use ciborium::de::Error as CborDeError;
+#[cfg(feature = "cbor")]
+use ciborium::ser::Error as CborSerError;
+#[cfg(feature = "msgpack")]
+use rmp_serde::decode::Error as MessagePackDeError;
+#[cfg(feature = "msgpack")]
+use rmp_serde::encode::Error as MessagePackSerError;
+#[cfg(feature = "cbor")]
+use std::io;
+use thiserror::Error as ThisError;
+#[cfg(feature = "toml")]
+use toml::de::Error as TomlDeError;
+#[cfg(feature = "toml")]
+use toml::ser::Error as TomlSerError;
+
+/// Errors when serializing or deserializing a chunk.
+#[derive(Debug, ThisError)]
+pub enum FormatError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[cfg(any(feature = "json", feature = "json-lines"))]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    TomlSerialize(#[from] TomlSerError),
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    TomlDeserialize(#[from] TomlDeError),
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackSerialize(#[from] MessagePackSerError),
+    #[cfg(feature = "msgpack")]
+    #[error(transparent)]
+    MessagePackDeserialize(#[from] MessagePackDeError),
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborSerialize(#[from] CborSerError<io::Error>),
+    #[cfg(feature = "cbor")]
+    #[error(transparent)]
+    CborDeserialize(#[from] CborDeError<io::Error>),
+    #[cfg(feature = "postcard")]
+    #[error(transparent)]
+    Postcard(#[from] postcard::Error),
+}
@@ -0,0 +1,56 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use std::collections::BTreeMap;
+
+/// Pretty printed JSON chunk format.
+///
+/// Suited to tables consumed by `jq`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Json;
+
+impl ChunkFormat for Json {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        let mut bytes = serde_json::to_vec_pretty(chunk)?;
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![serde_json::from_slice(bytes)?])
+    }
+
+    fn deserialize_maps_seed<S, V>(
+        &self,
+        bytes: &[u8],
+        seed: S,
+    ) -> Option<Result<Vec<V>, FormatError>>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Clone,
+    {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        let result = seed
+            .deserialize(&mut deserializer)
+            .and_then(|map| deserializer.end().map(|()| vec![map]));
+        Some(result.map_err(FormatError::from))
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{ChunkFormat, FormatError};
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// JSON Lines chunk format.
+///
+/// Each item is written on its own line as a single entry object keyed by hash.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct JsonLines;
+
+impl ChunkFormat for JsonLines {
+    fn extension(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        let mut bytes = Vec::new();
+        for (key, value) in chunk {
+            serde_json::to_writer(&mut bytes, &Entry { key, value })?;
+            bytes.push(b'\n');
+        }
+        Ok(bytes)
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        let mut chunk = BTreeMap::new();
+        for line in bytes.split(|&byte| byte == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let entry: BTreeMap<K, V> = serde_json::from_slice(line)?;
+            chunk.extend(entry);
+        }
+        Ok(chunk)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        let mut maps = Vec::new();
+        for line in bytes.split(|&byte| byte == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            maps.push(serde_json::from_slice(line)?);
+        }
+        Ok(maps)
+    }
+
+    fn deserialize_maps_seed<S, V>(
+        &self,
+        bytes: &[u8],
+        seed: S,
+    ) -> Option<Result<Vec<V>, FormatError>>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Clone,
+    {
+        let mut maps = Vec::new();
+        for line in bytes.split(|&byte| byte == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let mut deserializer = serde_json::Deserializer::from_slice(line);
+            let result = seed
+                .clone()
+                .deserialize(&mut deserializer)
+                .and_then(|map| deserializer.end().map(|()| map));
+            match result {
+                Ok(map) => maps.push(map),
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+        Some(Ok(maps))
+    }
+}
+
+/// Single entry object written as one line.
+struct Entry<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+impl<K: Serialize, V: Serialize> Serialize for Entry<'_, K, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(self.key, self.value)?;
+        map.end()
+    }
+}
@@ -0,0 +1,88 @@
+//! Serialization formats for chunk files.
+
+#[cfg(feature = "cbor")]
+pub use cbor::*;
+pub use format_error::*;
+#[cfg(feature = "json")]
+pub use json::*;
+#[cfg(feature = "json-lines")]
+pub use json_lines::*;
+#[cfg(feature = "msgpack")]
+pub use msgpack::*;
+#[cfg(feature = "postcard")]
+pub use postcard::*;
+#[cfg(feature = "toml")]
+pub use toml::*;
+pub use yaml::*;
+
+#[cfg(feature = "cbor")]
+mod cbor;
+mod format_error;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json-lines")]
+mod json_lines;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "postcard")]
+mod postcard;
+#[cfg(feature = "toml")]
+mod toml;
+mod yaml;
+
+use serde::Serialize;
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use std::collections::BTreeMap;
+
+/// Serialization format of a chunk file.
+///
+/// A chunk is a map of keys to items which is serialized to a single file.
+pub trait ChunkFormat: Clone + Send + Sync + 'static {
+    /// File extension of chunk files without the leading dot.
+    fn extension(&self) -> &'static str;
+
+    /// Serialize a chunk to bytes.
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize;
+
+    /// Deserialize a chunk from bytes.
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned;
+
+    /// Deserialize every map in a chunk as `D` instead of collecting it into a
+    /// [`BTreeMap`].
+    ///
+    /// `D` visits every entry in file order, including entries with a duplicate key.
+    /// Formats with one entry per line return one map per line.
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned;
+
+    /// Deserialize every map in a chunk with a seed that can carry state.
+    ///
+    /// Errors come from the format's own deserializer so they keep the line and column
+    /// of the chunk file where the format reports one.
+    ///
+    /// Returns `None` if the format has no deserializer to seed.
+    fn deserialize_maps_seed<S, V>(
+        &self,
+        _bytes: &[u8],
+        _seed: S,
+    ) -> Option<Result<Vec<V>, FormatError>>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Clone,
+    {
+        None
+    }
+
+    /// Whether items can be skipped without knowing their type.
+    ///
+    /// Key-only reads of formats that are not self-describing deserialize every item.
+    fn is_self_describing(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// `MessagePack` chunk format.
+///
+/// Compact binary format with named fields so items can gain optional fields.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MessagePack;
+
+impl ChunkFormat for MessagePack {
+    fn extension(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        Ok(rmp_serde::to_vec_named(chunk)?)
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![rmp_serde::from_slice(bytes)?])
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+/// Postcard chunk format.
+///
+/// The most compact and fastest format but not self-describing, so adding or
+/// removing fields of `T` makes existing chunks unreadable.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Postcard;
+
+impl ChunkFormat for Postcard {
+    fn extension(&self) -> &'static str {
+        "postcard"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        Ok(postcard::to_stdvec(chunk)?)
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![postcard::from_bytes(bytes)?])
+    }
+
+    fn is_self_describing(&self) -> bool {
+        false
+    }
+}
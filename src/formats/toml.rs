@@ -0,0 +1,57 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::{DeserializeOwned, DeserializeSeed, Error as DeError};
+use std::collections::BTreeMap;
+use toml::Deserializer as TomlDeserializer;
+use toml::de::Error as TomlDeError;
+
+/// TOML chunk format.
+///
+/// Each item is written as a table named by its hash.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Toml;
+
+impl ChunkFormat for Toml {
+    fn extension(&self) -> &'static str {
+        "toml"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        Ok(toml::to_string(chunk)?.into_bytes())
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(toml::from_slice(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![toml::from_slice(bytes)?])
+    }
+
+    fn deserialize_maps_seed<S, V>(
+        &self,
+        bytes: &[u8],
+        seed: S,
+    ) -> Option<Result<Vec<V>, FormatError>>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Clone,
+    {
+        let result = str::from_utf8(bytes)
+            .map_err(<TomlDeError as DeError>::custom)
+            .and_then(TomlDeserializer::parse)
+            .and_then(|deserializer| seed.deserialize(deserializer))
+            .map(|map| vec![map]);
+        Some(result.map_err(FormatError::from))
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{ChunkFormat, FormatError};
+use serde::Serialize;
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use std::collections::BTreeMap;
+
+/// YAML chunk format.
+///
+/// The default format as it is easily read, edited and diffed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Yaml;
+
+impl ChunkFormat for Yaml {
+    fn extension(&self) -> &'static str {
+        "yml"
+    }
+
+    fn serialize<K, V>(&self, chunk: &BTreeMap<K, V>) -> Result<Vec<u8>, FormatError>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+    {
+        Ok(serde_yaml::to_string(chunk)?.into_bytes())
+    }
+
+    fn deserialize<K, V>(&self, bytes: &[u8]) -> Result<BTreeMap<K, V>, FormatError>
+    where
+        K: DeserializeOwned + Ord,
+        V: DeserializeOwned,
+    {
+        Ok(serde_yaml::from_slice(bytes)?)
+    }
+
+    fn deserialize_maps<D>(&self, bytes: &[u8]) -> Result<Vec<D>, FormatError>
+    where
+        D: DeserializeOwned,
+    {
+        Ok(vec![serde_yaml::from_slice(bytes)?])
+    }
+
+    fn deserialize_maps_seed<S, V>(
+        &self,
+        bytes: &[u8],
+        seed: S,
+    ) -> Option<Result<Vec<V>, FormatError>>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Clone,
+    {
+        let deserializer = serde_yaml::Deserializer::from_slice(bytes);
+        Some(
+            seed.deserialize(deserializer)
+                .map(|map| vec![map])
+                .map_err(FormatError::from),
+        )
+    }
+}
@@ -1,5 +1,5 @@
 use miette::Diagnostic;
-use serde::de::Error as DeError;
+use serde::de::{Error as DeError, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter, Write};
@@ -7,10 +7,13 @@ use std::num::ParseIntError;
 use thiserror::Error;
 
 const HEXADECIMAL_RADIX: u32 = 16;
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
 
 /// Fixed-size byte array hash.
 ///
-/// Serializes to and from hexadecimal strings.
+/// Serializes to and from hexadecimal strings in human-readable formats and raw
+/// bytes in binary formats.
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Hash<const N: usize> {
     bytes: [u8; N],
@@ -77,7 +80,11 @@ impl<const N: usize> Serialize for Hash<N> {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_hex())
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.bytes)
+        }
     }
 }
 
@@ -86,8 +93,43 @@ impl<'de, const N: usize> Deserialize<'de> for Hash<N> {
     where
         D: Deserializer<'de>,
     {
-        let hex_str = String::deserialize(deserializer)?;
-        Hash::from_string(&hex_str).map_err(DeError::custom)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(HashVisitor)
+        } else {
+            deserializer.deserialize_bytes(HashVisitor)
+        }
+    }
+}
+
+/// Visitor accepting either a hexadecimal string or raw bytes.
+struct HashVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for HashVisitor<N> {
+    type Value = Hash<N>;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a {N} byte hash as hexadecimal string or bytes")
+    }
+
+    fn visit_str<E: DeError>(self, value: &str) -> Result<Self::Value, E> {
+        Hash::from_string(value).map_err(DeError::custom)
+    }
+
+    fn visit_bytes<E: DeError>(self, value: &[u8]) -> Result<Self::Value, E> {
+        let bytes: [u8; N] = value
+            .try_into()
+            .map_err(|_| DeError::invalid_length(value.len(), &self))?;
+        Ok(Hash::new(bytes))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = [0_u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| DeError::invalid_length(i, &self))?;
+        }
+        Ok(Hash::new(bytes))
     }
 }
 
@@ -114,6 +156,15 @@ fn to_byte(hex: &str) -> Result<u8, ParseIntError> {
     u8::from_str_radix(hex, HEXADECIMAL_RADIX)
 }
 
+/// Stable 64-bit FNV-1a hash of bytes.
+///
+/// Used where a hash must be identical across processes and releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
 /// Errors when parsing a `Hash` from a hexadecimal string.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Error, Diagnostic)]
 pub enum HashError {
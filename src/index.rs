@@ -0,0 +1,348 @@
+use crate::atomic_file::write_atomic;
+use crate::hash::fnv1a;
+use crate::lock_guard::acquire_lock;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use futures::TryStreamExt;
+use rogue_logging::Failure;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::pin::pin;
+use std::sync::Arc;
+use thiserror::Error as ThisError;
+use tokio::fs::{create_dir_all, read, remove_dir_all, remove_file};
+use tracing::{debug, trace};
+
+const INDEX_DIR_NAME: &str = "indexes";
+
+/// Key of an item in a secondary index.
+pub type IndexKey = String;
+
+/// Get the [`IndexKey`] of an item.
+type Extractor<T> = Arc<dyn Fn(&T) -> IndexKey + Send + Sync>;
+
+/// Secondary indexes registered on a [`Table`].
+pub(crate) struct Indexes<T> {
+    extractors: BTreeMap<String, Extractor<T>>,
+}
+
+impl<T> Indexes<T> {
+    /// Create an empty set of indexes.
+    pub(crate) fn new() -> Self {
+        Self {
+            extractors: BTreeMap::new(),
+        }
+    }
+
+    /// Whether no index is registered.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.extractors.is_empty()
+    }
+
+    /// Get the index changes caused by replacing `old` with `new`.
+    pub(crate) fn changes<const K: usize>(
+        &self,
+        hash: Hash<K>,
+        old: Option<&T>,
+        new: Option<&T>,
+    ) -> Vec<IndexChange<K>> {
+        self.extractors
+            .iter()
+            .filter_map(|(index, extractor)| {
+                let old = old.map(|item| extractor(item));
+                let new = new.map(|item| extractor(item));
+                (old != new).then(|| IndexChange {
+                    index: index.clone(),
+                    hash,
+                    old,
+                    new,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<T> Clone for Indexes<T> {
+    fn clone(&self) -> Self {
+        Self {
+            extractors: self.extractors.clone(),
+        }
+    }
+}
+
+/// Change to the key of an item in a secondary index.
+pub(crate) struct IndexChange<const K: usize> {
+    index: String,
+    hash: Hash<K>,
+    old: Option<IndexKey>,
+    new: Option<IndexKey>,
+}
+
+/// Chunk of a secondary index mapping each key to the hashes of matching items.
+type IndexChunk<const K: usize> = BTreeMap<IndexKey, BTreeSet<Hash<K>>>;
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Add a secondary index.
+    ///
+    /// - `extractor` gets the [`IndexKey`] of an item
+    /// - The index is stored in chunked files in the `indexes/{name}` directory
+    /// - The index is maintained by [`Table::set`], [`Table::set_many`] and [`Table::remove`]
+    ///
+    /// Use [`Table::rebuild_index`] to build the index for existing items.
+    ///
+    /// Fails with [`IndexError::InvalidName`] if `name` is empty or contains a path
+    /// separator or `..`.
+    pub fn with_index(
+        mut self,
+        name: impl Into<String>,
+        extractor: impl Fn(&T) -> IndexKey + Send + Sync + 'static,
+    ) -> Result<Self, Failure<TableAction>> {
+        let name = name.into();
+        validate_index_name(&name).map_err(|error| Failure::new(TableAction::AddIndex, error))?;
+        self.indexes.extractors.insert(name, Arc::new(extractor));
+        Ok(self)
+    }
+
+    /// Get a secondary index by name.
+    #[must_use]
+    pub fn index(&self, name: impl Into<String>) -> Index<'_, K, C, T, F> {
+        Index {
+            table: self,
+            name: name.into(),
+        }
+    }
+
+    /// Get the directory of a secondary index.
+    fn get_index_dir(&self, name: &str) -> PathBuf {
+        self.directory.join(INDEX_DIR_NAME).join(name)
+    }
+
+    /// Get the path of the index chunk containing `key`.
+    fn get_index_chunk_path(&self, name: &str, key: &str) -> PathBuf {
+        let chunk = Hash::new([fnv1a(key.as_bytes()).to_be_bytes()[0]]);
+        self.get_index_dir(name)
+            .join(format!("{chunk}.{}", self.format.extension()))
+    }
+
+    /// Read an index chunk.
+    ///
+    /// Returns an empty chunk if the file does not exist.
+    async fn read_index_chunk(
+        &self,
+        path: &PathBuf,
+    ) -> Result<IndexChunk<K>, Failure<TableAction>> {
+        if !path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        let bytes = read(path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadIndex, path))?;
+        self.format
+            .deserialize(&bytes)
+            .map_err(Failure::wrap_with_path(TableAction::ReadIndex, path))
+    }
+
+    /// Write an index chunk, removing the file if the chunk is empty.
+    async fn write_index_chunk(
+        &self,
+        path: &PathBuf,
+        chunk: &IndexChunk<K>,
+    ) -> Result<(), Failure<TableAction>> {
+        if chunk.is_empty() {
+            if path.is_file() {
+                remove_file(path)
+                    .await
+                    .map_err(Failure::wrap_with_path(TableAction::WriteIndex, path))?;
+            }
+            return Ok(());
+        }
+        let bytes = self
+            .format
+            .serialize(chunk)
+            .map_err(Failure::wrap_with_path(TableAction::WriteIndex, path))?;
+        write_atomic(path, bytes)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::WriteIndex, path))
+    }
+
+    /// Apply changes to the secondary indexes.
+    ///
+    /// Each index chunk is locked while it is updated.
+    pub(crate) async fn apply_index_changes(
+        &self,
+        changes: Vec<IndexChange<K>>,
+    ) -> Result<(), Failure<TableAction>> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut by_path: BTreeMap<PathBuf, Vec<(IndexKey, Hash<K>, bool)>> = BTreeMap::new();
+        for change in changes {
+            if let Some(old) = change.old {
+                let path = self.get_index_chunk_path(&change.index, &old);
+                by_path
+                    .entry(path)
+                    .or_default()
+                    .push((old, change.hash, false));
+            }
+            if let Some(new) = change.new {
+                let path = self.get_index_chunk_path(&change.index, &new);
+                by_path
+                    .entry(path)
+                    .or_default()
+                    .push((new, change.hash, true));
+            }
+        }
+        for (path, updates) in by_path {
+            let dir = path.parent().expect("index chunk should have a parent");
+            create_dir_all(dir)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::WriteIndex, dir))?;
+            let _lock = acquire_lock(&path, &self.lock_options)
+                .await
+                .map_err(Failure::wrap(TableAction::UpdateIndex))?;
+            let mut chunk = self.read_index_chunk(&path).await?;
+            for (key, hash, insert) in updates {
+                let hashes = chunk.entry(key.clone()).or_default();
+                if insert {
+                    hashes.insert(hash);
+                } else {
+                    hashes.remove(&hash);
+                    if hashes.is_empty() {
+                        chunk.remove(&key);
+                    }
+                }
+            }
+            trace!(path = %path.display(), "Updating index chunk");
+            self.write_index_chunk(&path, &chunk).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + DeserializeOwned,
+{
+    /// Rebuild a secondary index from every item in the table.
+    ///
+    /// Existing index files are replaced. Writers are stopped with [`Table::exclusive`]
+    /// while the index is rebuilt. Chunks are streamed so only the index is held in
+    /// memory.
+    ///
+    /// Returns the number of items indexed
+    pub async fn rebuild_index(&self, name: &str) -> Result<usize, Failure<TableAction>> {
+        let Some(extractor) = self.indexes.extractors.get(name) else {
+            return Err(Failure::new(
+                TableAction::RebuildIndex,
+                IndexError::Unknown {
+                    name: name.to_owned(),
+                },
+            ));
+        };
+        let _exclusive = self
+            .exclusive()
+            .await
+            .map_err(Failure::wrap(TableAction::RebuildIndex))?;
+        let mut chunks: BTreeMap<PathBuf, IndexChunk<K>> = BTreeMap::new();
+        let mut count = 0;
+        let mut table_chunks = pin!(self.chunks());
+        while let Some((_, table_chunk)) = table_chunks
+            .try_next()
+            .await
+            .map_err(Failure::wrap(TableAction::RebuildIndex))?
+        {
+            count += table_chunk.len();
+            for (hash, item) in &table_chunk {
+                let key = extractor(item);
+                let path = self.get_index_chunk_path(name, &key);
+                chunks
+                    .entry(path)
+                    .or_default()
+                    .entry(key)
+                    .or_default()
+                    .insert(*hash);
+            }
+        }
+        let dir = self.get_index_dir(name);
+        if dir.exists() {
+            remove_dir_all(&dir)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::RebuildIndex, &dir))?;
+        }
+        create_dir_all(&dir)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::RebuildIndex, &dir))?;
+        for (path, chunk) in chunks {
+            self.write_index_chunk(&path, &chunk).await?;
+        }
+        debug!(index = name, items = count, "Rebuilt index");
+        Ok(count)
+    }
+}
+
+/// Secondary index of a [`Table`].
+///
+/// Created with [`Table::index`].
+pub struct Index<'a, const K: usize, const C: usize, T, F> {
+    table: &'a Table<K, C, T, F>,
+    name: String,
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Index<'_, K, C, T, F> {
+    /// Get the hashes of items with the key.
+    pub async fn get(&self, key: &str) -> Result<BTreeSet<Hash<K>>, Failure<TableAction>> {
+        validate_index_name(&self.name)
+            .map_err(|error| Failure::new(TableAction::ReadIndex, error))?;
+        let path = self.table.get_index_chunk_path(&self.name, key);
+        let mut chunk = self.table.read_index_chunk(&path).await?;
+        let hashes = chunk.remove(key).unwrap_or_default();
+        trace!(index = self.name, key, count = hashes.len(), "Get index");
+        Ok(hashes)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Index<'_, K, C, T, F>
+where
+    T: Clone + DeserializeOwned,
+{
+    /// Get the items with the key.
+    ///
+    /// - Hashes in the index without a matching item are skipped
+    /// - Fails if no index is registered with the name
+    pub async fn get_items(&self, key: &str) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        if !self.table.indexes.extractors.contains_key(&self.name) {
+            return Err(Failure::new(
+                TableAction::ReadIndex,
+                IndexError::Unknown {
+                    name: self.name.clone(),
+                },
+            ));
+        }
+        let mut items = BTreeMap::new();
+        for hash in self.get(key).await? {
+            if let Some(item) = self.table.get(hash).await? {
+                items.insert(hash, item);
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Check that an index name is a single directory name within `indexes`.
+fn validate_index_name(name: &str) -> Result<(), IndexError> {
+    if name.is_empty() || name == "." || name.contains("..") || name.contains(['/', '\\']) {
+        return Err(IndexError::InvalidName {
+            name: name.to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Errors when using a secondary index.
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum IndexError {
+    #[error("No index is registered with name: {name}")]
+    Unknown { name: String },
+    #[error("Index name must not be empty or contain a path separator or `..`: {name}")]
+    InvalidName { name: String },
+}
@@ -0,0 +1,110 @@
+use crate::sidecar::SidecarLookup;
+use crate::table::get_chunk_hash;
+use crate::{ChunkFormat, Compression, Hash, Table, TableAction};
+use futures::{TryStreamExt, future};
+use rogue_logging::Failure;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tokio::fs::read;
+use tracing::{debug, trace};
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Check if an item exists.
+    ///
+    /// Only the keys of the chunk are deserialized so this is cheaper than
+    /// [`Table::get`] for large items.
+    pub async fn contains(&self, hash: Hash<K>) -> Result<bool, Failure<TableAction>> {
+        let chunk_hash = get_chunk_hash(hash);
+        let lookup = self.lookup_sidecar(hash).await;
+        if let SidecarLookup::Found(found) = lookup {
+            trace!(hash = %hash, found, "Contains item");
+            return Ok(found);
+        }
+        let keys = self
+            .load_chunk_keys(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::Contains))?;
+        if let SidecarLookup::Stale(stamp) = lookup {
+            self.write_sidecar(chunk_hash, stamp, &keys).await;
+        }
+        let found = keys.contains(&hash);
+        trace!(hash = %hash, found, "Contains item");
+        Ok(found)
+    }
+
+    /// Count the items.
+    ///
+    /// Only the keys of each chunk are deserialized.
+    pub async fn len(&self) -> Result<usize, Failure<TableAction>> {
+        let count = self
+            .keys()
+            .try_fold(0, |count, _| future::ready(Ok(count + 1)))
+            .await
+            .map_err(Failure::wrap(TableAction::Len))?;
+        trace!(count, "Count items");
+        Ok(count)
+    }
+
+    /// Check if the table has no items.
+    ///
+    /// See [`Table::len`].
+    pub async fn is_empty(&self) -> Result<bool, Failure<TableAction>> {
+        Ok(self.len().await? == 0)
+    }
+
+    /// Read the keys of a chunk, including entries in the write-ahead log.
+    ///
+    /// - Items are skipped without being deserialized if the format is
+    ///   self-describing
+    /// - Returns an empty set if the chunk does not exist
+    pub(crate) async fn load_chunk_keys(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<BTreeSet<Hash<K>>, Failure<TableAction>> {
+        if !self.format.is_self_describing() {
+            let chunk = self.load_chunk_shared(hash).await?;
+            return Ok(chunk.into_keys().collect());
+        }
+        let _lock = self.lock_chunk_shared(hash).await?;
+        let wal_keys = self.read_wal_keys(hash).await?;
+        let mut keys = match self.find_chunk_path(hash) {
+            Some(path) => self.read_chunk_keys(&path).await?,
+            None => BTreeSet::new(),
+        };
+        for (key, is_set) in wal_keys {
+            if is_set {
+                keys.insert(key);
+            } else {
+                keys.remove(&key);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Read the keys of a chunk file without deserializing the items.
+    #[expect(
+        clippy::zero_sized_map_values,
+        reason = "chunk formats only deserialize maps"
+    )]
+    async fn read_chunk_keys(
+        &self,
+        path: &Path,
+    ) -> Result<BTreeSet<Hash<K>>, Failure<TableAction>> {
+        debug!(path = %path.display(), "Reading chunk keys");
+        let bytes = read(path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadChunk, path))?;
+        let bytes = Compression::from_path(path)
+            .decompress(bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Decompress, path))?;
+        let chunk: BTreeMap<Hash<K>, IgnoredAny> = self
+            .format
+            .deserialize(&bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Deserialize, path))?;
+        Ok(chunk.into_keys().collect())
+    }
+}
@@ -0,0 +1,451 @@
+use crate::atomic_file::write_atomic;
+use crate::wal::merge_wal;
+use crate::{ChunkFormat, Compression, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, Error as DeError, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs::{create_dir_all, read, rename};
+use tracing::{info, trace, warn};
+
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Problem skipped by [`Table::get_all_lenient`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReadDiagnostic {
+    /// Path of the chunk file.
+    pub path: PathBuf,
+    /// Key of the item, or `None` if the whole chunk could not be read.
+    pub key: Option<String>,
+    /// Error reported by the deserializer, including the location if known.
+    pub message: String,
+    /// Path the chunk or item was moved to, if it was quarantined.
+    pub quarantined: Option<PathBuf>,
+}
+
+impl Display for ReadDiagnostic {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", self.path.display())?;
+        if let Some(key) = &self.key {
+            write!(formatter, " {key}")?;
+        }
+        write!(formatter, ": {}", self.message)
+    }
+}
+
+/// Items read by [`Table::get_all_lenient`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LenientRead<const K: usize, T> {
+    /// Items that were read successfully.
+    pub items: BTreeMap<Hash<K>, T>,
+    /// Chunks and items that were skipped.
+    pub diagnostics: Vec<ReadDiagnostic>,
+}
+
+/// Result of leniently reading a chunk file.
+pub(crate) struct LenientChunk<const K: usize, T> {
+    pub(crate) items: BTreeMap<Hash<K>, T>,
+    /// Entries that could not be read, or `None` if the whole chunk could not be read.
+    rejected: Option<BTreeMap<RawKey, Value>>,
+    pub(crate) diagnostics: Vec<ReadDiagnostic>,
+}
+
+impl<const K: usize, T> LenientChunk<K, T> {
+    fn new(items: BTreeMap<Hash<K>, T>) -> Self {
+        Self {
+            items,
+            rejected: Some(BTreeMap::new()),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn unreadable(path: &Path, message: impl Display) -> Self {
+        Self {
+            items: BTreeMap::new(),
+            rejected: None,
+            diagnostics: vec![ReadDiagnostic {
+                path: path.to_path_buf(),
+                key: None,
+                message: message.to_string(),
+                quarantined: None,
+            }],
+        }
+    }
+}
+
+/// Key of a chunk entry before it is validated as a [`Hash`].
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum RawKey {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl RawKey {
+    /// Parse the key as a `Hash<K>`.
+    pub(crate) fn to_hash<const K: usize>(&self) -> Result<Hash<K>, String> {
+        match self {
+            RawKey::Text(text) => Hash::from_string(text).map_err(|error| error.to_string()),
+            RawKey::Bytes(bytes) => {
+                let bytes: [u8; K] = bytes.as_slice().try_into().map_err(|_| {
+                    format!("Expected a {K} byte hash but found {} bytes", bytes.len())
+                })?;
+                Ok(Hash::new(bytes))
+            }
+        }
+    }
+}
+
+impl Display for RawKey {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RawKey::Text(text) => write!(formatter, "{text}"),
+            RawKey::Bytes(bytes) => bytes
+                .iter()
+                .try_for_each(|byte| write!(formatter, "{byte:02x}")),
+        }
+    }
+}
+
+impl Serialize for RawKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            RawKey::Text(text) => serializer.serialize_str(text),
+            RawKey::Bytes(bytes) => serializer.serialize_bytes(bytes),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RawKeyVisitor)
+    }
+}
+
+/// Visitor accepting any scalar or byte sequence as a key.
+struct RawKeyVisitor;
+
+impl<'de> Visitor<'de> for RawKeyVisitor {
+    type Value = RawKey;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a string or bytes")
+    }
+
+    fn visit_bool<E: DeError>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(RawKey::Text(value.to_string()))
+    }
+
+    fn visit_i64<E: DeError>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(RawKey::Text(value.to_string()))
+    }
+
+    fn visit_u64<E: DeError>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(RawKey::Text(value.to_string()))
+    }
+
+    fn visit_f64<E: DeError>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(RawKey::Text(value.to_string()))
+    }
+
+    fn visit_str<E: DeError>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(RawKey::Text(value.to_owned()))
+    }
+
+    fn visit_bytes<E: DeError>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(RawKey::Bytes(value.to_vec()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(RawKey::Bytes(bytes))
+    }
+}
+
+/// Seed deserializing the item of one key in a chunk map and skipping the others.
+struct EntrySeed<'a, T> {
+    key: &'a RawKey,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Clone for EntrySeed<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for EntrySeed<'_, T> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for EntrySeed<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a map of items")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some(key) = map.next_key::<RawKey>()? {
+            if key == *self.key {
+                map.next_value::<T>()?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Move chunks and items that [`Table::get_all_lenient`] can't read into a
+    /// `quarantine` directory in the table directory.
+    ///
+    /// - Chunks that can't be parsed at all are moved as they are
+    /// - Items that can't be read are written to a file in the same format and
+    ///   removed from the chunk
+    ///
+    /// Default: `false`
+    #[must_use]
+    pub fn with_quarantine(mut self, quarantine: bool) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+
+    /// Get a unique path in the quarantine directory for a chunk file.
+    fn get_quarantine_path(&self, path: &Path) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration should be valid")
+            .as_nanos();
+        let file_name = path
+            .file_name()
+            .expect("chunk should have a name")
+            .to_string_lossy();
+        self.directory
+            .join(QUARANTINE_DIR_NAME)
+            .join(format!("{timestamp}-{file_name}"))
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Get all items, skipping chunks and items that can't be read.
+    ///
+    /// - Items are deserialized individually if a chunk can't be read as a whole
+    /// - Each skipped chunk or item is reported with the error from the deserializer
+    /// - Skipped chunks and items are quarantined if enabled by
+    ///   [`Table::with_quarantine`]
+    ///
+    /// Items are read individually through a YAML [`Value`] so items in binary formats
+    /// may only be reported as a whole chunk.
+    pub async fn get_all_lenient(&self) -> Result<LenientRead<K, T>, Failure<TableAction>> {
+        let mut read = LenientRead {
+            items: BTreeMap::new(),
+            diagnostics: Vec::new(),
+        };
+        let chunks = self
+            .list_chunks()
+            .await
+            .map_err(Failure::wrap(TableAction::GetAllLenient))?;
+        for chunk_hash in chunks {
+            let (items, diagnostics) = self
+                .load_chunk_lenient(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::GetAllLenient))?;
+            read.items.extend(items);
+            read.diagnostics.extend(diagnostics);
+        }
+        trace!(
+            count = read.items.len(),
+            skipped = read.diagnostics.len(),
+            "Get all items leniently"
+        );
+        Ok(read)
+    }
+
+    /// Leniently read a chunk by hash, including entries in the write-ahead log.
+    async fn load_chunk_lenient(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<(BTreeMap<Hash<K>, T>, Vec<ReadDiagnostic>), Failure<TableAction>> {
+        let entries = self.read_wal_chunk(hash).await?;
+        let Some(path) = self.find_chunk_path(hash) else {
+            let mut items = BTreeMap::new();
+            merge_wal(&mut items, entries);
+            return Ok((items, Vec::new()));
+        };
+        let mut chunk = {
+            let _lock = self.lock_chunk_shared(hash).await?;
+            self.read_chunk_lenient(&path).await?
+        };
+        if self.quarantine && !chunk.diagnostics.is_empty() {
+            let _writer = self.lock_writer().await?;
+            let _lock = self.lock_chunk(hash).await?;
+            chunk = self.read_chunk_lenient(&path).await?;
+            if !chunk.diagnostics.is_empty() {
+                self.quarantine_chunk(hash, &path, &mut chunk).await?;
+            }
+        }
+        for diagnostic in &chunk.diagnostics {
+            warn!(%diagnostic, "Skipped unreadable chunk data");
+        }
+        let mut items = chunk.items;
+        merge_wal(&mut items, entries);
+        Ok((items, chunk.diagnostics))
+    }
+
+    /// Read a chunk file, deserializing items individually if it can't be read as a
+    /// whole.
+    pub(crate) async fn read_chunk_lenient(
+        &self,
+        path: &Path,
+    ) -> Result<LenientChunk<K, T>, Failure<TableAction>> {
+        let bytes = read(path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadChunk, path))?;
+        let bytes = match Compression::from_path(path).decompress(bytes) {
+            Ok(bytes) => bytes,
+            Err(error) => return Ok(LenientChunk::unreadable(path, error)),
+        };
+        let version = self.get_pending_version(path).await?;
+        if version.is_none() {
+            match self.format.deserialize(&bytes) {
+                Ok(items) => return Ok(LenientChunk::new(items)),
+                Err(error) if !self.format.is_self_describing() => {
+                    return Ok(LenientChunk::unreadable(path, error));
+                }
+                Err(_) => {}
+            }
+        }
+        let entries: BTreeMap<RawKey, Value> = match self.format.deserialize(&bytes) {
+            Ok(entries) => entries,
+            Err(error) => return Ok(LenientChunk::unreadable(path, error)),
+        };
+        let mut chunk = LenientChunk::new(BTreeMap::new());
+        let mut rejected = BTreeMap::new();
+        for (key, value) in entries {
+            match self.read_entry(&key, value.clone(), version) {
+                Ok((hash, item)) => {
+                    chunk.items.insert(hash, item);
+                }
+                Err(message) => {
+                    let message = match version {
+                        Some(_) => message,
+                        None => self.locate_entry_error(&bytes, &key).unwrap_or(message),
+                    };
+                    chunk.diagnostics.push(ReadDiagnostic {
+                        path: path.to_path_buf(),
+                        key: Some(key.to_string()),
+                        message,
+                        quarantined: None,
+                    });
+                    rejected.insert(key, value);
+                }
+            }
+        }
+        chunk.rejected = Some(rejected);
+        Ok(chunk)
+    }
+
+    /// Read a single chunk entry, migrating it if the chunk has a pending version.
+    fn read_entry(
+        &self,
+        key: &RawKey,
+        value: Value,
+        version: Option<u32>,
+    ) -> Result<(Hash<K>, T), String> {
+        let hash = key.to_hash()?;
+        let value = match version {
+            Some(version) => self
+                .migrations
+                .apply(value, version)
+                .map_err(|error| error.to_string())?,
+            None => value,
+        };
+        let item = serde_yaml::from_value(value).map_err(|error| error.to_string())?;
+        Ok((hash, item))
+    }
+
+    /// Deserialize the item of one key from the chunk bytes to get the error with its
+    /// line and column.
+    ///
+    /// Returns `None` if the format can't locate errors or the item deserializes.
+    fn locate_entry_error(&self, bytes: &[u8], key: &RawKey) -> Option<String> {
+        let seed = EntrySeed::<T> {
+            key,
+            phantom: PhantomData,
+        };
+        let result = self.format.deserialize_maps_seed(bytes, seed)?;
+        result.err().map(|error| error.to_string())
+    }
+
+    /// Move the unreadable parts of a chunk into the quarantine directory.
+    ///
+    /// The chunk lock must be held.
+    async fn quarantine_chunk(
+        &self,
+        hash: Hash<C>,
+        path: &Path,
+        chunk: &mut LenientChunk<K, T>,
+    ) -> Result<(), Failure<TableAction>> {
+        let target = self.get_quarantine_path(path);
+        let dir = target
+            .parent()
+            .expect("quarantine path should have a parent");
+        create_dir_all(dir)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::Quarantine, dir))?;
+        if let Some(rejected) = &chunk.rejected {
+            let bytes = self
+                .format
+                .serialize(rejected)
+                .map_err(Failure::wrap_with_path(TableAction::Serialize, &target))?;
+            let bytes = Compression::from_path(path)
+                .compress(bytes)
+                .map_err(Failure::wrap_with_path(TableAction::Compress, &target))?;
+            write_atomic(&target, bytes)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::Quarantine, &target))?;
+            self.write_chunk(hash, chunk.items.clone()).await?;
+        } else {
+            rename(path, &target)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::Quarantine, &target))?;
+            self.invalidate_cached_chunk(hash);
+            self.remove_sidecar(hash).await;
+        }
+        info!(from = %path.display(), to = %target.display(), "Quarantined chunk data");
+        for diagnostic in &mut chunk.diagnostics {
+            diagnostic.quarantined = Some(target.clone());
+        }
+        Ok(())
+    }
+}
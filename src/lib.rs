@@ -4,13 +4,49 @@
 //! and the performance cost of serializing large numbers of items to a flat file
 //! format that can be manually edited and version controlled.
 
+pub use cache::*;
+pub use compression::*;
+pub use exclusive::*;
 pub use file_table::*;
+pub use formats::*;
 pub use hash::*;
+pub use index::*;
+pub use lenient::*;
+pub use lock_guard::*;
+pub use manifest::*;
+pub use migration::*;
+pub use reshard::*;
+pub use scan::*;
+pub use stream::*;
 pub use table::*;
+pub use transaction::*;
+pub use update::*;
+pub use verify::*;
+pub use version::*;
+pub use wal::*;
 
+mod atomic_file;
+mod cache;
+mod compression;
+mod exclusive;
 mod file_table;
+mod formats;
 mod hash;
+mod index;
+mod keys;
+mod lenient;
 mod lock_guard;
+mod manifest;
+mod migration;
+mod reshard;
+mod scan;
+mod sidecar;
+mod stream;
 mod table;
 #[cfg(test)]
 mod tests;
+mod transaction;
+mod update;
+mod verify;
+mod version;
+mod wal;
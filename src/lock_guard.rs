@@ -1,59 +1,807 @@
-use crate::TableAction;
+use crate::atomic_file::get_temp_path;
+use crate::{ChunkFormat, FileTable, Hash, Table, TableAction};
 use rogue_logging::Failure;
-use std::fs::remove_file;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, Metadata, TryLockError, read_to_string, remove_file, rename, write};
+use std::hash::{BuildHasher, Hasher};
 use std::io;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
-use tokio::fs::OpenOptions;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error as ThisError;
+use tokio::fs::{OpenOptions, metadata, read, read_dir};
+use tokio::io::AsyncWriteExt;
+use tokio::task::{JoinHandle, spawn};
 use tokio::time::sleep;
-use tracing::trace;
+use tracing::{trace, warn};
 
-const LOCK_ACQUIRE_SLEEP_MILLIS: u64 = 50;
-const LOCK_ACQUIRE_TIMEOUT: u64 = 2;
 const LOCK_FILE_EXTENSION: &str = "lock";
+const WRITER_LOCK_PREFIX: &str = "writer-";
+
+/// Default maximum time to wait for a lock.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default time to wait between attempts to acquire a lock.
+pub const DEFAULT_LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Default age after which a lock is considered stale if its owner can't be checked.
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_mins(10);
+
+/// Shortest wait between refreshes of a held lock.
+///
+/// The acquired time of a lock is recorded in whole seconds.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Options for acquiring lock files.
+///
+/// Every process writing to a table should use the same [`LockOptions::advisory`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LockOptions {
+    /// Maximum time to wait for a lock.
+    ///
+    /// Default: [`DEFAULT_LOCK_TIMEOUT`]
+    pub timeout: Duration,
+    /// Wait between attempts to acquire a lock.
+    ///
+    /// Default: [`LockBackoff::Fixed`] of [`DEFAULT_LOCK_RETRY_DELAY`]
+    pub backoff: LockBackoff,
+    /// Fail immediately if the lock is held instead of waiting.
+    ///
+    /// Default: `false`
+    pub fail_fast: bool,
+    /// Use OS advisory locks (`flock` or `LockFileEx`) on the lock file instead of
+    /// relying on its existence.
+    ///
+    /// Advisory locks are released by the OS when the owner exits so they are never
+    /// stale, but they are unreliable on some network file systems.
+    ///
+    /// Default: `false`
+    pub advisory: bool,
+    /// Age after which a lock is broken if it can't be checked whether its owner is
+    /// still running.
+    ///
+    /// Applies to locks acquired on another host, locks without owner info and
+    /// platforms other than Linux. Locks of running processes on this host are never
+    /// broken.
+    ///
+    /// Held locks are refreshed every quarter of this age, or every second if that is
+    /// shorter, so a long running holder is not broken.
+    ///
+    /// `None` only breaks locks whose owner process no longer exists.
+    ///
+    /// Default: [`DEFAULT_STALE_AFTER`]
+    pub stale_after: Option<Duration>,
+    /// Read chunks without a lock.
+    ///
+    /// Only takes effect while atomic writes are enabled so readers observe either
+    /// the previous or the new chunk. Otherwise reads take a shared lock that
+    /// excludes writers.
+    ///
+    /// Default: `true`
+    pub lock_free_reads: bool,
+}
+
+impl Default for LockOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_LOCK_TIMEOUT,
+            backoff: LockBackoff::Fixed(DEFAULT_LOCK_RETRY_DELAY),
+            fail_fast: false,
+            advisory: false,
+            stale_after: Some(DEFAULT_STALE_AFTER),
+            lock_free_reads: true,
+        }
+    }
+}
+
+/// Wait between attempts to acquire a lock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LockBackoff {
+    /// Wait the same duration between every attempt.
+    Fixed(Duration),
+    /// Double the wait after every attempt up to `max`.
+    ///
+    /// Each wait is randomly shortened by up to half so competing processes don't
+    /// retry in step.
+    Exponential {
+        /// Wait after the first attempt.
+        initial: Duration,
+        /// Maximum wait between attempts.
+        max: Duration,
+    },
+}
+
+impl LockBackoff {
+    /// Get the wait after the first attempt.
+    fn initial(self) -> Duration {
+        match self {
+            LockBackoff::Fixed(delay) => delay,
+            LockBackoff::Exponential { initial, .. } => initial,
+        }
+    }
+
+    /// Get the wait after the attempt following `delay`.
+    fn next(self, delay: Duration) -> Duration {
+        match self {
+            LockBackoff::Fixed(delay) => delay,
+            LockBackoff::Exponential { max, .. } => delay.saturating_mul(2).min(max),
+        }
+    }
+
+    /// Apply random jitter to a wait.
+    fn with_jitter(self, delay: Duration) -> Duration {
+        match self {
+            LockBackoff::Fixed(_) => delay,
+            LockBackoff::Exponential { .. } => {
+                let half = delay / 2;
+                let range = u64::try_from(half.as_nanos())
+                    .unwrap_or(u64::MAX)
+                    .saturating_add(1);
+                let random = RandomState::new().build_hasher().finish();
+                half + Duration::from_nanos(random % range)
+            }
+        }
+    }
+}
+
+/// Owner of a lock recorded in the lock file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LockInfo {
+    /// Process ID of the owner.
+    pub pid: u32,
+    /// Hostname of the owner.
+    pub host: String,
+    /// Seconds since the Unix epoch when the lock was acquired.
+    pub acquired: u64,
+}
+
+impl LockInfo {
+    /// Get the owner info of the current process.
+    pub(crate) fn current() -> Self {
+        Self {
+            pid: process::id(),
+            host: get_hostname(),
+            acquired: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
+
+impl Display for LockInfo {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "process {} on {} since {}",
+            self.pid, self.host, self.acquired
+        )
+    }
+}
 
 /// RAII guard that removes a lock file when dropped.
 pub(crate) struct LockGuard {
     path: PathBuf,
+    /// Lock file holding an advisory lock.
+    ///
+    /// The lock is released when the file is closed after it is removed.
+    file: Option<File>,
+    /// Whether the advisory lock is shared with other readers.
+    is_shared: bool,
+    /// Owner recorded in the lock file, updated each time it is refreshed.
+    ///
+    /// `None` for shared locks which don't record their owner.
+    info: Arc<Mutex<Option<LockInfo>>>,
+    /// Task refreshing the acquired time of the lock file while it is held.
+    refresh: Option<JoinHandle<()>>,
 }
+
+impl LockGuard {
+    /// Refresh the acquired time in the lock file while the lock is held.
+    ///
+    /// Otherwise a holder on another host is broken once the lock is older than
+    /// [`LockOptions::stale_after`].
+    fn keep_fresh(mut self, stale_after: Duration) -> Self {
+        let interval = (stale_after / 4).max(MIN_REFRESH_INTERVAL);
+        let path = self.path.clone();
+        let info = self.info.clone();
+        self.refresh = Some(spawn(async move {
+            loop {
+                sleep(interval).await;
+                let mut info = info.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(current) = info.as_mut() else {
+                    return;
+                };
+                if !refresh_lock_file(&path, current) {
+                    return;
+                }
+            }
+        }));
+        self
+    }
+}
+
 impl Drop for LockGuard {
     fn drop(&mut self) {
+        if let Some(refresh) = &self.refresh {
+            refresh.abort();
+        }
+        if self.is_shared
+            && let Some(file) = &self.file
+            && file.try_lock().is_err()
+        {
+            trace!(path = %self.path.display(), "Keeping lock file held by other readers");
+            return;
+        }
+        let mut info = self.info.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(info) = info.take() {
+            let current = read_to_string(&self.path)
+                .ok()
+                .and_then(|yaml| serde_yaml::from_str::<LockInfo>(&yaml).ok());
+            if current != Some(info) {
+                warn!(path = %self.path.display(), "Keeping lock file acquired by another owner after this lock was broken");
+                return;
+            }
+        }
+        trace!(path = %self.path.display(), "Lock released");
         let _ = remove_file(&self.path);
     }
 }
 
+/// Rewrite a lock file with the current time if it is still held by `info`.
+///
+/// The lock file is replaced by renaming a temporary file so it is never partial.
+///
+/// Returns `false` if the lock is no longer held by `info`
+fn refresh_lock_file(path: &Path, info: &mut LockInfo) -> bool {
+    let current = read_to_string(path)
+        .ok()
+        .and_then(|yaml| serde_yaml::from_str::<LockInfo>(&yaml).ok());
+    if current.as_ref() != Some(info) {
+        warn!(path = %path.display(), "Stopped refreshing lock acquired by another owner");
+        return false;
+    }
+    let refreshed = LockInfo::current();
+    let temp_path = get_temp_path(path);
+    let result = serde_yaml::to_string(&refreshed)
+        .map_err(io::Error::other)
+        .and_then(|yaml| write(&temp_path, yaml))
+        .and_then(|()| rename(&temp_path, path));
+    match result {
+        Ok(()) => {
+            trace!(path = %path.display(), acquired = refreshed.acquired, "Refreshed lock");
+            *info = refreshed;
+        }
+        Err(error) => {
+            warn!(path = %path.display(), %error, "Failed to refresh lock");
+            let _ = remove_file(&temp_path);
+        }
+    }
+    true
+}
+
 /// Acquire a lock
 ///
-/// If the lock is already in use then wait
+/// If the lock is already in use then wait according to the [`LockOptions`].
+///
+/// A lock is broken if its owner process no longer exists or, if that can't be
+/// checked, it is older than [`LockOptions::stale_after`].
+///
+/// Fails with [`LockError`] if the lock is still held.
 pub(crate) async fn acquire_lock(
     path: impl AsRef<Path>,
+    options: &LockOptions,
 ) -> Result<LockGuard, Failure<TableAction>> {
-    let start = Instant::now();
-    let timeout = Duration::from_secs(LOCK_ACQUIRE_TIMEOUT);
-    let mut lock: PathBuf = path.as_ref().to_path_buf();
-    lock.set_extension(LOCK_FILE_EXTENSION);
+    acquire(path.as_ref(), options, false).await
+}
+
+/// Acquire a lock that is shared with other readers but excludes [`acquire_lock`].
+///
+/// Only [`LockOptions::advisory`] locks can be shared. Otherwise this is the same as
+/// [`acquire_lock`].
+pub(crate) async fn acquire_shared_lock(
+    path: impl AsRef<Path>,
+    options: &LockOptions,
+) -> Result<LockGuard, Failure<TableAction>> {
+    acquire(path.as_ref(), options, true).await
+}
+
+/// Try to acquire a lock without waiting.
+///
+/// A stale lock is broken first, see [`acquire_lock`].
+///
+/// Returns `None` if the lock is held.
+pub(crate) async fn try_acquire_lock(
+    path: impl AsRef<Path>,
+    options: &LockOptions,
+) -> Result<Option<LockGuard>, Failure<TableAction>> {
+    try_acquire(&get_lock_path(path.as_ref()), options, false).await
+}
+
+async fn acquire(
+    path: &Path,
+    options: &LockOptions,
+    is_shared: bool,
+) -> Result<LockGuard, Failure<TableAction>> {
+    let lock = get_lock_path(path);
+    let mut wait = LockWait::new(options);
     loop {
-        if OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&lock)
+        if let Some(guard) = try_acquire(&lock, options, is_shared).await? {
+            return Ok(guard);
+        }
+        wait.wait(&lock).await?;
+    }
+}
+
+async fn try_acquire(
+    lock: &Path,
+    options: &LockOptions,
+    is_shared: bool,
+) -> Result<Option<LockGuard>, Failure<TableAction>> {
+    loop {
+        let guard = if options.advisory {
+            try_lock_advisory(lock, is_shared)
+        } else {
+            try_lock_file(lock).await
+        };
+        if let Some(guard) =
+            guard.map_err(Failure::wrap_with_path(TableAction::AcquireLock, lock))?
+        {
+            trace!(path = %lock.display(), is_shared, "Lock acquired");
+            return Ok(Some(match options.stale_after {
+                Some(stale_after) if !options.advisory => guard.keep_fresh(stale_after),
+                _ => guard,
+            }));
+        }
+        if options.advisory || !break_if_stale(lock, options).await {
+            return Ok(None);
+        }
+    }
+}
+
+/// Acquire a lock shared by writers that excludes [`acquire_exclusive_lock`].
+///
+/// With [`LockOptions::advisory`] this is a shared lock on `path`. Otherwise each
+/// writer creates its own lock file next to `path` once `path` is not locked.
+pub(crate) async fn acquire_writer_lock(
+    path: &Path,
+    options: &LockOptions,
+) -> Result<LockGuard, Failure<TableAction>> {
+    if options.advisory {
+        return acquire_shared_lock(path, options).await;
+    }
+    let lock = get_lock_path(path);
+    loop {
+        wait_for_release(&lock, options).await?;
+        let guard = acquire_lock(path.with_file_name(get_writer_name()), options).await?;
+        if !lock.exists() {
+            return Ok(guard);
+        }
+        trace!(path = %lock.display(), "Exclusive lock acquired while registering writer");
+        drop(guard);
+    }
+}
+
+/// Acquire a lock that excludes [`acquire_writer_lock`] then wait for existing
+/// writers to finish.
+pub(crate) async fn acquire_exclusive_lock(
+    path: &Path,
+    options: &LockOptions,
+) -> Result<LockGuard, Failure<TableAction>> {
+    let guard = acquire_lock(path, options).await?;
+    if !options.advisory {
+        wait_for_writers(path, options).await?;
+    }
+    Ok(guard)
+}
+
+/// Wait until a lock file does not exist.
+async fn wait_for_release(lock: &Path, options: &LockOptions) -> Result<(), Failure<TableAction>> {
+    let mut wait = LockWait::new(options);
+    while lock.exists() {
+        if break_if_stale(lock, options).await {
+            continue;
+        }
+        wait.wait(lock).await?;
+    }
+    Ok(())
+}
+
+/// Wait until every writer lock file next to `path` is removed.
+async fn wait_for_writers(path: &Path, options: &LockOptions) -> Result<(), Failure<TableAction>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut wait = LockWait::new(options);
+    loop {
+        let mut remaining = Vec::new();
+        let mut entries = read_dir(dir)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::AcquireLock, dir))?;
+        while let Some(entry) = entries
+            .next_entry()
             .await
-            .is_ok()
+            .map_err(Failure::wrap_with_path(TableAction::AcquireLock, dir))?
         {
-            trace!(path = %lock.display(), "Lock acquired");
-            return Ok(LockGuard { path: lock });
-        }
-        if start.elapsed() > timeout {
-            return Err(Failure::new(
-                TableAction::AcquireLock,
-                io::Error::new(
-                    io::ErrorKind::TimedOut,
-                    "Exceeded timeout for acquiring lock",
-                ),
-            )
-            .with_path(&lock));
-        }
-        trace!(path = %lock.display(), "Lock busy, waiting");
-        sleep(Duration::from_millis(LOCK_ACQUIRE_SLEEP_MILLIS)).await;
+            let lock = entry.path();
+            let is_writer = entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(WRITER_LOCK_PREFIX);
+            if is_writer && !break_if_stale(&lock, options).await {
+                remaining.push(lock);
+            }
+        }
+        let Some(lock) = remaining.first() else {
+            return Ok(());
+        };
+        trace!(writers = remaining.len(), "Waiting for writers to finish");
+        wait.wait(lock).await?;
+    }
+}
+
+/// Get a unique name for the lock file of a writer.
+fn get_writer_name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let counter = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{WRITER_LOCK_PREFIX}{}-{counter}", process::id())
+}
+
+/// Find every lock file in a directory and its subdirectories.
+///
+/// Returns an empty list if the directory does not exist.
+pub(crate) async fn find_lock_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    find_files(dir, LOCK_FILE_EXTENSION).await
+}
+
+/// Find every file with `extension` in a directory and its subdirectories.
+///
+/// Returns an empty list if the directory does not exist.
+pub(crate) async fn find_files(dir: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = match read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(error),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|found| found == extension) {
+                files.push(path);
+            }
+        }
     }
+    Ok(files)
+}
+
+/// Waits between attempts to acquire a lock according to the [`LockOptions`].
+struct LockWait<'a> {
+    options: &'a LockOptions,
+    start: Instant,
+    delay: Duration,
+}
+
+impl<'a> LockWait<'a> {
+    fn new(options: &'a LockOptions) -> Self {
+        Self {
+            options,
+            start: Instant::now(),
+            delay: options.backoff.initial(),
+        }
+    }
+
+    /// Wait before the next attempt.
+    ///
+    /// Fails with [`LockError`] if [`LockOptions::fail_fast`] is set or the timeout is
+    /// exceeded.
+    async fn wait(&mut self, lock: &Path) -> Result<(), Failure<TableAction>> {
+        let waited = self.start.elapsed();
+        if self.options.fail_fast || waited >= self.options.timeout {
+            let holder = read_lock_info(lock).await;
+            let error = if self.options.fail_fast {
+                LockError::Busy { holder }
+            } else {
+                LockError::Timeout { waited, holder }
+            };
+            return Err(Failure::new(TableAction::AcquireLock, error).with_path(lock));
+        }
+        let wait = self
+            .options
+            .backoff
+            .with_jitter(self.delay)
+            .min(self.options.timeout.saturating_sub(waited));
+        trace!(path = %lock.display(), wait = ?wait, "Lock busy, waiting");
+        sleep(wait).await;
+        self.delay = self.options.backoff.next(self.delay);
+        Ok(())
+    }
+}
+
+/// Try to acquire a lock by creating the lock file.
+///
+/// Returns `None` if the lock file already exists.
+async fn try_lock_file(lock: &Path) -> io::Result<Option<LockGuard>> {
+    let Ok(mut file) = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(lock)
+        .await
+    else {
+        return Ok(None);
+    };
+    let info = LockInfo::current();
+    let yaml = serde_yaml::to_string(&info).map_err(io::Error::other)?;
+    let guard = LockGuard {
+        path: lock.to_path_buf(),
+        file: None,
+        is_shared: false,
+        info: Arc::new(Mutex::new(None)),
+        refresh: None,
+    };
+    file.write_all(yaml.as_bytes()).await?;
+    file.flush().await?;
+    *guard.info.lock().unwrap_or_else(PoisonError::into_inner) = Some(info);
+    Ok(Some(guard))
+}
+
+/// Try to acquire an advisory lock on the lock file.
+///
+/// The lock is not acquired if the previous owner removed the lock file while
+/// waiting for it.
+///
+/// Shared locks don't record their owner as the lock file is shared by many readers.
+///
+/// Returns `None` if the lock is held.
+fn try_lock_advisory(lock: &Path, is_shared: bool) -> io::Result<Option<LockGuard>> {
+    let file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock)?;
+    let result = if is_shared {
+        file.try_lock_shared()
+    } else {
+        file.try_lock()
+    };
+    match result {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(error)) => return Err(error),
+    }
+    let Ok(current) = lock.metadata() else {
+        return Ok(None);
+    };
+    if !is_same_file(&file.metadata()?, &current) {
+        return Ok(None);
+    }
+    let mut guard = LockGuard {
+        path: lock.to_path_buf(),
+        file: Some(file),
+        is_shared,
+        info: Arc::new(Mutex::new(None)),
+        refresh: None,
+    };
+    if let Some(file) = &mut guard.file
+        && !is_shared
+    {
+        let info = LockInfo::current();
+        let yaml = serde_yaml::to_string(&info).map_err(io::Error::other)?;
+        file.set_len(0)?;
+        file.write_all(yaml.as_bytes())?;
+        *guard.info.lock().unwrap_or_else(PoisonError::into_inner) = Some(info);
+    }
+    Ok(Some(guard))
+}
+
+/// Whether two metadata refer to the same file.
+#[cfg(unix)]
+fn is_same_file(first: &Metadata, second: &Metadata) -> bool {
+    first.dev() == second.dev() && first.ino() == second.ino()
+}
+
+/// Open files can't be removed on this platform so the path always refers to the
+/// open file.
+#[cfg(not(unix))]
+fn is_same_file(_first: &Metadata, _second: &Metadata) -> bool {
+    true
+}
+
+/// Get the path of the lock file for a path.
+fn get_lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.to_path_buf();
+    lock.set_extension(LOCK_FILE_EXTENSION);
+    lock
+}
+
+/// Read the owner of a lock.
+///
+/// Returns `None` if the lock does not exist or has no owner info.
+async fn read_lock_info(lock: &Path) -> Option<LockInfo> {
+    let bytes = read(lock).await.ok()?;
+    serde_yaml::from_slice(&bytes).ok()
+}
+
+/// Remove a lock if it is stale.
+///
+/// The lock file is read again immediately before removal so a lock that was
+/// released and acquired by another process in the meantime is kept.
+///
+/// Returns `true` if the lock was removed
+async fn break_if_stale(lock: &Path, options: &LockOptions) -> bool {
+    let Ok(bytes) = read(lock).await else {
+        return false;
+    };
+    let info: Option<LockInfo> = serde_yaml::from_slice(&bytes).ok();
+    let age = match &info {
+        Some(info) => SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()
+            .map(|now| now.saturating_sub(Duration::from_secs(info.acquired))),
+        None => metadata(lock)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| modified.elapsed().ok()),
+    };
+    let Some(reason) = get_stale_reason(info.as_ref(), age, options) else {
+        return false;
+    };
+    if read(lock).await.ok().as_deref() != Some(bytes.as_slice()) {
+        return false;
+    }
+    warn!(
+        path = %lock.display(),
+        pid = info.as_ref().map(|info| info.pid),
+        host = info.as_ref().map(|info| info.host.as_str()),
+        age = ?age,
+        reason,
+        "Breaking stale lock"
+    );
+    remove_file(lock).is_ok()
+}
+
+/// Get the reason a lock is stale.
+///
+/// Returns `None` if the lock is not stale.
+fn get_stale_reason(
+    info: Option<&LockInfo>,
+    age: Option<Duration>,
+    options: &LockOptions,
+) -> Option<&'static str> {
+    if let Some(info) = info
+        && info.host == get_hostname()
+    {
+        let is_running = if info.pid == process::id() {
+            Some(true)
+        } else {
+            is_process_running(info.pid)
+        };
+        match is_running {
+            Some(true) => return None,
+            Some(false) => return Some("owner process no longer exists"),
+            None => {}
+        }
+    }
+    if let (Some(age), Some(stale_after)) = (age, options.stale_after)
+        && age > stale_after
+    {
+        return Some("lock is older than the stale threshold");
+    }
+    None
+}
+
+/// Get the hostname of this machine.
+fn get_hostname() -> String {
+    if cfg!(target_os = "linux")
+        && let Ok(hostname) = read_to_string("/proc/sys/kernel/hostname")
+    {
+        return hostname.trim().to_owned();
+    }
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .unwrap_or_default()
+}
+
+/// Whether a process is running on this machine.
+///
+/// Returns `None` if it can't be determined on this platform.
+fn is_process_running(pid: u32) -> Option<bool> {
+    cfg!(target_os = "linux").then(|| Path::new("/proc").join(pid.to_string()).exists())
+}
+
+/// Describe the owner of a lock for an error message.
+fn format_holder(holder: Option<&LockInfo>) -> String {
+    holder.map_or_else(|| "an unknown owner".to_owned(), ToString::to_string)
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Set the options for acquiring lock files.
+    #[must_use]
+    pub fn with_lock_options(mut self, options: LockOptions) -> Self {
+        self.lock_options = options;
+        self
+    }
+
+    /// Remove every stale lock file in the table directory.
+    ///
+    /// With [`LockOptions::advisory`] a lock file is removed if no process holds its
+    /// lock, otherwise see [`LockOptions::stale_after`].
+    ///
+    /// Returns the number of locks removed
+    pub async fn clear_stale_locks(&self) -> Result<usize, Failure<TableAction>> {
+        let locks = find_lock_files(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(
+                TableAction::ClearStaleLocks,
+                &self.directory,
+            ))?;
+        let mut removed = 0;
+        for path in locks {
+            let is_removed = if self.lock_options.advisory {
+                try_lock_advisory(&path, false)
+                    .map_err(Failure::wrap_with_path(TableAction::ClearStaleLocks, &path))?
+                    .is_some()
+            } else {
+                break_if_stale(&path, &self.lock_options).await
+            };
+            if is_removed {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Acquire a shared lock for reading a chunk.
+    ///
+    /// Returns `None` if chunks are read without a lock.
+    ///
+    /// See [`LockOptions::lock_free_reads`].
+    pub(crate) async fn lock_chunk_shared(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<Option<LockGuard>, Failure<TableAction>> {
+        if self.atomic_writes && self.lock_options.lock_free_reads {
+            return Ok(None);
+        }
+        let guard = acquire_shared_lock(self.get_base_chunk_path(hash), &self.lock_options).await?;
+        Ok(Some(guard))
+    }
+}
+
+impl<const K: usize, const C: usize> FileTable<K, C> {
+    /// Set the options for acquiring lock files.
+    #[must_use]
+    pub fn with_lock_options(mut self, options: LockOptions) -> Self {
+        self.lock_options = options;
+        self
+    }
+}
+
+/// Errors when a lock could not be acquired.
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum LockError {
+    #[error("Lock is held by {}", format_holder(.holder.as_ref()))]
+    Busy { holder: Option<LockInfo> },
+    #[error(
+        "Timed out after {waited:?} waiting for lock held by {}",
+        format_holder(.holder.as_ref())
+    )]
+    Timeout {
+        waited: Duration,
+        holder: Option<LockInfo>,
+    },
 }
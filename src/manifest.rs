@@ -0,0 +1,266 @@
+use crate::atomic_file::write_atomic;
+use crate::lock_guard::acquire_lock;
+use crate::{ChunkFormat, Compression, Table, TableAction};
+use rogue_logging::Failure;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use thiserror::Error as ThisError;
+use tokio::fs::{create_dir_all, read, read_dir};
+use tracing::{debug, trace};
+
+pub(crate) const MANIFEST_FILE_NAME: &str = "flat_db.yml";
+
+/// Layout of a [`Table`] recorded in its directory.
+///
+/// Written when a table is first opened and validated every time it is opened again
+/// so a directory is never read with a different layout.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Manifest {
+    /// Number of bytes in each key.
+    pub key_width: usize,
+    /// Number of key bytes used to determine the chunk.
+    pub chunk_width: usize,
+    /// File extension of the chunk format.
+    pub format: String,
+    /// Name of the item schema.
+    ///
+    /// Empty if the table was opened without a name.
+    pub schema: String,
+    /// Version of the item schema.
+    pub version: u32,
+    /// Chunks written with a newer schema version than `version`.
+    ///
+    /// Only populated while a migration is in progress.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub chunk_versions: BTreeMap<String, u32>,
+}
+
+impl Manifest {
+    /// Get the fields that differ from `expected`.
+    ///
+    /// - An older version is ignored if `can_migrate` is true
+    /// - The schema name is only compared if both manifests have one
+    fn mismatches(&self, expected: &Manifest, can_migrate: bool) -> Vec<ManifestError> {
+        let is_schema_named = !expected.schema.is_empty() && !self.schema.is_empty();
+        let fields = [
+            (
+                "key_width",
+                expected.key_width.to_string(),
+                self.key_width.to_string(),
+            ),
+            (
+                "chunk_width",
+                expected.chunk_width.to_string(),
+                self.chunk_width.to_string(),
+            ),
+            ("format", expected.format.clone(), self.format.clone()),
+            (
+                "schema",
+                expected.schema.clone(),
+                if is_schema_named {
+                    self.schema.clone()
+                } else {
+                    expected.schema.clone()
+                },
+            ),
+            (
+                "version",
+                expected.version.to_string(),
+                if can_migrate {
+                    expected.version.to_string()
+                } else {
+                    self.version.to_string()
+                },
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, expected, actual)| expected != actual)
+            .map(|(field, expected, actual)| ManifestError::Mismatch {
+                field,
+                expected,
+                actual,
+            })
+            .collect()
+    }
+}
+
+impl<const K: usize, const C: usize, T> Table<K, C, T> {
+    /// Open a [`Table`] using the default [`Yaml`](crate::Yaml) format.
+    ///
+    /// See [`Table::init`].
+    pub async fn open(directory: impl Into<PathBuf>) -> Result<Self, Failure<TableAction>> {
+        Self::new(directory).init().await
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Set the name and version of the item schema recorded in the manifest.
+    ///
+    /// The name is only validated on open if it is set both here and in the
+    /// manifest, so it should be stable across refactors and compiler versions.
+    ///
+    /// Default: no name and version `0`
+    #[must_use]
+    pub fn with_schema(mut self, name: impl Into<String>, version: u32) -> Self {
+        self.schema = name.into();
+        self.version = version;
+        self.is_migrated = Arc::new(AtomicBool::new(false));
+        self
+    }
+
+    /// Get the path to the manifest file.
+    pub(crate) fn get_manifest_path(&self) -> PathBuf {
+        self.directory.join(MANIFEST_FILE_NAME)
+    }
+
+    /// Get the manifest describing this table.
+    #[must_use]
+    pub fn manifest(&self) -> Manifest {
+        Manifest {
+            key_width: K,
+            chunk_width: C,
+            format: self.format.extension().to_owned(),
+            schema: self.schema.clone(),
+            version: self.version,
+            chunk_versions: BTreeMap::new(),
+        }
+    }
+
+    /// Read the manifest from the table directory.
+    ///
+    /// Returns `None` if the manifest does not exist.
+    pub async fn read_manifest(&self) -> Result<Option<Manifest>, Failure<TableAction>> {
+        let path = self.get_manifest_path();
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let bytes = read(&path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadManifest, &path))?;
+        let manifest = serde_yaml::from_slice(&bytes)
+            .map_err(Failure::wrap_with_path(TableAction::ReadManifest, &path))?;
+        Ok(Some(manifest))
+    }
+
+    /// Write the manifest to the table directory.
+    pub(crate) async fn write_manifest(
+        &self,
+        manifest: &Manifest,
+    ) -> Result<(), Failure<TableAction>> {
+        let path = self.get_manifest_path();
+        debug!(path = %path.display(), "Writing manifest");
+        let yaml = serde_yaml::to_string(manifest)
+            .map_err(Failure::wrap_with_path(TableAction::WriteManifest, &path))?;
+        write_atomic(&path, yaml)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::WriteManifest, &path))
+    }
+
+    /// Check that every chunk file in the directory has a name of this chunk width.
+    ///
+    /// Used to validate tables written before they had a manifest.
+    async fn check_chunk_widths(&self) -> Result<(), Failure<TableAction>> {
+        let mut widths = BTreeSet::new();
+        let mut dir = read_dir(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(
+                TableAction::ReadDir,
+                &self.directory,
+            ))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            let path = entry.path();
+            if let Some(width) = self.get_chunk_width(&path)
+                && path.is_file()
+            {
+                widths.insert(width);
+            }
+        }
+        let others: Vec<String> = widths
+            .into_iter()
+            .filter(|width| *width != C)
+            .map(|width| width.to_string())
+            .collect();
+        if others.is_empty() {
+            return Ok(());
+        }
+        let error = ManifestError::Mismatch {
+            field: "chunk_width",
+            expected: C.to_string(),
+            actual: others.join(", "),
+        };
+        Err(Failure::new(TableAction::Open, error).with_path(&self.directory))
+    }
+
+    /// Get the chunk width of a file named like a chunk file of any width.
+    ///
+    /// Returns `None` if the name is not a hex chunk hash with the format extension.
+    fn get_chunk_width(&self, path: &Path) -> Option<usize> {
+        let path = Compression::from_path(path).strip_from_path(path);
+        if path.extension()? != self.format.extension() {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        let is_hex = stem.bytes().all(|byte| byte.is_ascii_hexdigit());
+        let bytes = stem.as_bytes().chunks_exact(2);
+        (is_hex && !stem.is_empty() && bytes.remainder().is_empty()).then_some(bytes.len())
+    }
+
+    /// Validate the manifest, or write it if this is the first use of the directory.
+    ///
+    /// - The directory is created if it does not exist
+    /// - Fails if the key width, chunk width, format, schema name or version differ
+    /// - Without a manifest, fails if existing chunk files have a different chunk
+    ///   width
+    /// - An older version is accepted if it can be upgraded by [`Table::with_migrations`]
+    /// - Interrupted transactions are recovered with [`Table::recover`]
+    pub async fn init(self) -> Result<Self, Failure<TableAction>> {
+        create_dir_all(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::Open, &self.directory))?;
+        let path = self.get_manifest_path();
+        let lock = acquire_lock(&path, &self.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::Open))?;
+        let expected = self.manifest();
+        if let Some(actual) = self.read_manifest().await? {
+            let can_migrate = self.can_migrate_from(actual.version);
+            let mut mismatches = actual.mismatches(&expected, can_migrate).into_iter();
+            if let Some(first) = mismatches.next() {
+                let mut failure = Failure::new(TableAction::Open, first).with_path(&path);
+                for other in mismatches {
+                    failure = failure
+                        .with_related(Failure::new(TableAction::Open, other).with_path(&path));
+                }
+                return Err(failure);
+            }
+            trace!(path = %path.display(), "Manifest is valid");
+        } else {
+            self.check_chunk_widths().await?;
+            self.write_manifest(&expected).await?;
+        }
+        drop(lock);
+        self.recover().await?;
+        Ok(self)
+    }
+}
+
+/// Errors when validating a [`Manifest`].
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum ManifestError {
+    #[error(
+        "Table manifest does not match\nField: {field}\nExpected: {expected}\nActual: {actual}"
+    )]
+    Mismatch {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
@@ -0,0 +1,275 @@
+use crate::lock_guard::acquire_lock;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use thiserror::Error as ThisError;
+use tracing::{debug, info, trace};
+
+/// Upgrade an item from one schema version to the next.
+///
+/// The item is provided as a [`Value`] so fields can be added, renamed or removed
+/// before it is deserialized as the new item type.
+pub type MigrationStep = fn(Value) -> Value;
+
+/// Ordered registry of [`MigrationStep`].
+///
+/// The step at index `n` upgrades an item from version `n` to version `n + 1` so the
+/// current schema version is the number of steps.
+///
+/// A chunk may be migrated again if the process stops between writing a chunk and
+/// recording its version, so steps should be idempotent.
+#[derive(Clone, Debug, Default)]
+pub struct Migrations {
+    steps: Vec<MigrationStep>,
+}
+
+impl Migrations {
+    /// Create an empty [`Migrations`] registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the step upgrading from the current version to the next.
+    #[must_use]
+    pub fn with_step(mut self, step: MigrationStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Current schema version.
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        u32::try_from(self.steps.len()).expect("number of steps should fit in u32")
+    }
+
+    /// Whether no steps are registered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Upgrade an item from `version` to the current version.
+    pub(crate) fn apply(&self, mut value: Value, version: u32) -> Result<Value, MigrationError> {
+        let start = usize::try_from(version).expect("version should fit in usize");
+        let steps = self
+            .steps
+            .get(start..)
+            .ok_or(MigrationError::MissingStep { version })?;
+        for step in steps {
+            value = step(value);
+        }
+        Ok(value)
+    }
+}
+
+/// Progress of [`Table::migrate_all`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MigrationProgress {
+    /// Path of the chunk that was migrated.
+    pub path: PathBuf,
+    /// Number of chunks processed so far.
+    pub completed: usize,
+    /// Total number of chunks.
+    pub total: usize,
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Upgrade items written with an older schema version.
+    ///
+    /// The schema version becomes the number of steps in `migrations`.
+    ///
+    /// Items are migrated lazily as chunks are read and [`Table::migrate_all`] rewrites
+    /// every chunk. Versions are recorded in the manifest so the table must be opened
+    /// with [`Table::init`], and the format must be self-describing.
+    #[must_use]
+    pub fn with_migrations(mut self, migrations: Migrations) -> Self {
+        self.version = migrations.version();
+        self.migrations = migrations;
+        self.is_migrated = Arc::new(AtomicBool::new(false));
+        self
+    }
+
+    /// Whether a manifest at `version` can be upgraded by the registered migrations.
+    pub(crate) fn can_migrate_from(&self, version: u32) -> bool {
+        version < self.version && self.version <= self.migrations.version()
+    }
+
+    /// Get the schema version of a chunk if it is older than the current version.
+    ///
+    /// The manifest is no longer read once its version is current.
+    pub(crate) async fn get_pending_version(
+        &self,
+        path: &Path,
+    ) -> Result<Option<u32>, Failure<TableAction>> {
+        if self.migrations.is_empty() || self.is_migrated.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let Some(chunk_hash) = self.parse_chunk_path(path) else {
+            return Ok(None);
+        };
+        let Some(manifest) = self.read_manifest().await? else {
+            return Ok(None);
+        };
+        if manifest.version >= self.version {
+            trace!(version = manifest.version, "Migration complete");
+            self.is_migrated.store(true, Ordering::Relaxed);
+            return Ok(None);
+        }
+        let version = manifest
+            .chunk_versions
+            .get(&chunk_hash.to_hex())
+            .copied()
+            .unwrap_or(manifest.version);
+        Ok((version < self.version).then_some(version))
+    }
+
+    /// Record that a chunk has been written with the current schema version.
+    pub(crate) async fn record_chunk_version(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<(), Failure<TableAction>> {
+        if self.migrations.is_empty() || self.is_migrated.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let _lock = acquire_lock(self.get_manifest_path(), &self.lock_options).await?;
+        let Some(mut manifest) = self.read_manifest().await? else {
+            return Ok(());
+        };
+        if manifest.version >= self.version {
+            self.is_migrated.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+        trace!(chunk = %hash, version = self.version, "Recording chunk version");
+        manifest.chunk_versions.insert(hash.to_hex(), self.version);
+        self.write_manifest(&manifest).await
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Deserialize a chunk written with an older schema version.
+    pub(crate) fn migrate_chunk(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+        version: u32,
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        debug!(path = %path.display(), from = version, to = self.version, "Migrating chunk");
+        let values: BTreeMap<Hash<K>, Value> = self
+            .format
+            .deserialize(bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Deserialize, path))?;
+        let mut chunk = BTreeMap::new();
+        for (hash, value) in values {
+            let with_context = |failure: Failure<TableAction>| {
+                failure
+                    .with_path(path)
+                    .with("hash", hash.to_hex())
+                    .with("from_version", version.to_string())
+                    .with("to_version", self.version.to_string())
+            };
+            let value = self
+                .migrations
+                .apply(value, version)
+                .map_err(Failure::wrap_with(TableAction::Migrate, with_context))?;
+            let item = serde_yaml::from_value(value)
+                .map_err(Failure::wrap_with(TableAction::Migrate, with_context))?;
+            chunk.insert(hash, item);
+        }
+        Ok(chunk)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Rewrite every chunk with the current schema version.
+    ///
+    /// - Each chunk is locked while it is migrated
+    /// - `progress` is called after each chunk
+    /// - The manifest version is updated once every chunk is migrated
+    ///
+    /// Returns the number of items migrated
+    pub async fn migrate_all(
+        &self,
+        mut progress: impl FnMut(MigrationProgress),
+    ) -> Result<usize, Failure<TableAction>> {
+        let manifest_path = self.get_manifest_path();
+        if self.read_manifest().await?.is_none() {
+            return Err(
+                Failure::new(TableAction::MigrateAll, MigrationError::MissingManifest)
+                    .with_path(&manifest_path),
+            );
+        }
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::MigrateAll))?;
+        let chunks = self.list_chunks().await?;
+        let total = chunks.len();
+        let mut migrated = 0;
+        for (index, chunk_hash) in chunks.into_iter().enumerate() {
+            let _lock = self
+                .lock_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::MigrateAll))?;
+            let Some(path) = self.find_chunk_path(chunk_hash) else {
+                continue;
+            };
+            if self.get_pending_version(&path).await?.is_some() {
+                let chunk = self
+                    .load_chunk(chunk_hash)
+                    .await
+                    .map_err(Failure::wrap(TableAction::MigrateAll))?;
+                migrated += chunk.len();
+                self.write_chunk(chunk_hash, chunk)
+                    .await
+                    .map_err(Failure::wrap(TableAction::MigrateAll))?;
+            }
+            progress(MigrationProgress {
+                path,
+                completed: index + 1,
+                total,
+            });
+        }
+        let _lock = acquire_lock(&manifest_path, &self.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::MigrateAll))?;
+        let mut manifest = self
+            .read_manifest()
+            .await?
+            .unwrap_or_else(|| self.manifest());
+        if manifest.version < self.version {
+            manifest.version = self.version;
+            manifest.chunk_versions.clear();
+            self.write_manifest(&manifest).await?;
+        }
+        self.is_migrated.store(true, Ordering::Relaxed);
+        info!(
+            items = migrated,
+            chunks = total,
+            version = self.version,
+            "Migrated all chunks"
+        );
+        Ok(migrated)
+    }
+}
+
+/// Errors when migrating items to a newer schema version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ThisError)]
+pub enum MigrationError {
+    #[error("No migration step from version {version}")]
+    MissingStep { version: u32 },
+    #[error("Table must be opened with a manifest to be migrated")]
+    MissingManifest,
+}
@@ -0,0 +1,249 @@
+use crate::cache::ChunkCache;
+use crate::lock_guard::{acquire_exclusive_lock, acquire_lock};
+use crate::table::group_by_chunk;
+use crate::{ChunkFormat, FileTable, FileTableAction, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use thiserror::Error as ThisError;
+use tokio::fs::{create_dir_all, remove_dir, remove_file, rename};
+use tracing::{debug, info, trace};
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Get a table with the same directory and options but a different chunk width.
+    fn with_chunk_width<const C2: usize>(&self) -> Table<K, C2, T, F> {
+        Table {
+            directory: self.directory.clone(),
+            format: self.format.clone(),
+            compression: self.compression,
+            schema: self.schema.clone(),
+            version: self.version,
+            migrations: self.migrations.clone(),
+            is_migrated: self.is_migrated.clone(),
+            indexes: self.indexes.clone(),
+            read_ahead: self.read_ahead,
+            concurrency: self.concurrency,
+            wal_checkpoint_bytes: self.wal_checkpoint_bytes,
+            wal_tail: Arc::new(Mutex::new(None)),
+            cache: self
+                .cache
+                .as_ref()
+                .map(|cache| ChunkCache::new(cache.capacity())),
+            lock_options: self.lock_options,
+            atomic_writes: self.atomic_writes,
+            sidecars: self.sidecars,
+            quarantine: self.quarantine,
+            exclusive_lock: self.exclusive_lock.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Rewrite every chunk to a chunk width of `C2`.
+    ///
+    /// - Each old chunk is locked while it is read and while it is removed
+    /// - Item counts are verified before the old chunk files are removed
+    /// - An interrupted reshard is resumed: chunks that already have a width of `C2`
+    ///   are merged with the remaining old chunks
+    /// - The manifest, if any, is updated with the new chunk width
+    ///
+    /// Writers are stopped with [`Table::exclusive`] while resharding. Writers using the
+    /// old chunk width must not be restarted afterwards.
+    pub async fn reshard<const C2: usize>(
+        &self,
+    ) -> Result<Table<K, C2, T, F>, Failure<TableAction>> {
+        let resharded = self.with_chunk_width::<C2>();
+        if C2 == C {
+            return Ok(resharded);
+        }
+        if C2 > K {
+            return Err(Failure::new(
+                TableAction::Reshard,
+                ReshardError::InvalidChunkWidth { key: K, chunk: C2 },
+            ));
+        }
+        let exclusive = self
+            .exclusive()
+            .await
+            .map_err(Failure::wrap(TableAction::Reshard))?;
+        exclusive
+            .checkpoint_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::Reshard))?;
+        let mut items = BTreeMap::new();
+        let existing = resharded.list_chunks().await?;
+        if !existing.is_empty() {
+            debug!(chunks = existing.len(), "Resuming interrupted reshard");
+        }
+        for chunk_hash in existing {
+            let chunk = resharded
+                .load_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+            items.extend(chunk);
+        }
+        let old_chunks = self.list_chunks().await?;
+        for chunk_hash in &old_chunks {
+            let _lock = self
+                .lock_chunk(*chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+            let chunk = self
+                .load_chunk(*chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+            items.extend(chunk);
+        }
+        let expected = items.len();
+        info!(
+            items = expected,
+            from = old_chunks.len(),
+            from_width = C,
+            to_width = C2,
+            "Resharding table"
+        );
+        let new_chunks = group_by_chunk::<K, C2, T>(items);
+        for (chunk_hash, chunk) in new_chunks {
+            let _lock = resharded
+                .lock_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+            resharded
+                .write_chunk(chunk_hash, chunk)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+        }
+        let actual = resharded
+            .get_all()
+            .await
+            .map_err(Failure::wrap(TableAction::Reshard))?
+            .len();
+        if actual != expected {
+            return Err(Failure::new(
+                TableAction::Reshard,
+                ReshardError::CountMismatch { expected, actual },
+            )
+            .with_path(&self.directory));
+        }
+        for chunk_hash in old_chunks {
+            let _lock = self
+                .lock_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Reshard))?;
+            for path in self.find_chunk_paths(chunk_hash) {
+                trace!(path = %path.display(), "Removing old chunk");
+                remove_file(&path)
+                    .await
+                    .map_err(Failure::wrap_with_path(TableAction::RemoveChunk, &path))?;
+            }
+            self.remove_sidecar(chunk_hash).await;
+        }
+        if self.read_manifest().await?.is_some() {
+            resharded.write_manifest(&resharded.manifest()).await?;
+        }
+        debug!(items = actual, "Resharding complete");
+        Ok(resharded)
+    }
+}
+
+impl<const K: usize, const C: usize> FileTable<K, C> {
+    /// Move every file into chunk directories with a width of `C2`.
+    ///
+    /// - Writers are stopped with a table-wide lock while files are moved
+    /// - Each file is locked while it is moved
+    /// - Files are moved by renaming so they are never copied
+    /// - File counts are verified before the old chunk directories are removed
+    /// - Fails if chunk directories with a width of `C2` already exist
+    pub async fn reshard<const C2: usize>(
+        &self,
+    ) -> Result<FileTable<K, C2>, Failure<FileTableAction>> {
+        let resharded = FileTable::<K, C2>::new(self.directory.clone(), self.extension.clone())
+            .with_read_ahead(self.read_ahead)
+            .with_concurrency(self.concurrency)
+            .with_lock_options(self.lock_options);
+        if C2 == C {
+            return Ok(resharded);
+        }
+        if C2 > K {
+            return Err(Failure::new(
+                FileTableAction::Reshard,
+                ReshardError::InvalidChunkWidth { key: K, chunk: C2 },
+            ));
+        }
+        let _exclusive = acquire_exclusive_lock(&self.get_table_lock_path(), &self.lock_options)
+            .await
+            .map_err(Failure::wrap(FileTableAction::AcquireLock))
+            .map_err(Failure::wrap(FileTableAction::Reshard))?;
+        let existing = resharded.get_all().await?;
+        if !existing.is_empty() {
+            return Err(Failure::new(
+                FileTableAction::Reshard,
+                ReshardError::TargetNotEmpty {
+                    count: existing.len(),
+                },
+            )
+            .with_path(&self.directory));
+        }
+        let paths = self.get_all().await?;
+        let expected = paths.len();
+        info!(
+            files = expected,
+            from_width = C,
+            to_width = C2,
+            "Resharding file table"
+        );
+        for (hash, path) in &paths {
+            let new_path = resharded.get_path(*hash);
+            let new_dir = new_path.parent().expect("stored path should have a parent");
+            create_dir_all(new_dir)
+                .await
+                .map_err(Failure::wrap_with_path(FileTableAction::CreateDir, new_dir))?;
+            let _lock = acquire_lock(path, &self.lock_options)
+                .await
+                .map_err(Failure::wrap(FileTableAction::AcquireLock))
+                .map_err(Failure::wrap(FileTableAction::Reshard))?;
+            trace!(from = %path.display(), to = %new_path.display(), "Moving file");
+            rename(path, &new_path)
+                .await
+                .map_err(Failure::wrap_with_path(FileTableAction::MoveFile, path))?;
+        }
+        let actual = resharded.get_all().await?.len();
+        if actual != expected {
+            return Err(Failure::new(
+                FileTableAction::Reshard,
+                ReshardError::CountMismatch { expected, actual },
+            )
+            .with_path(&self.directory));
+        }
+        let old_dirs: BTreeSet<Hash<C>> = paths
+            .keys()
+            .map(|hash| hash.truncate::<C>().expect("should be able to truncate"))
+            .collect();
+        for chunk_hash in old_dirs {
+            let dir = self.directory.join(chunk_hash.to_hex());
+            if remove_dir(&dir).await.is_err() {
+                debug!(path = %dir.display(), "Old chunk directory is not empty");
+            }
+        }
+        debug!(files = actual, "Resharding complete");
+        Ok(resharded)
+    }
+}
+
+/// Errors when resharding a [`Table`] or [`FileTable`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ThisError)]
+pub enum ReshardError {
+    #[error("Chunk width {chunk} is greater than key width {key}")]
+    InvalidChunkWidth { key: usize, chunk: usize },
+    #[error("{count} chunks or files already exist with the new chunk width")]
+    TargetNotEmpty { count: usize },
+    #[error("Item count changed during resharding\nExpected: {expected}\nActual: {actual}")]
+    CountMismatch { expected: usize, actual: usize },
+}
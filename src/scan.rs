@@ -0,0 +1,236 @@
+use crate::table::get_chunk_hash;
+use crate::{ChunkFormat, FileTable, FileTableAction, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+use std::path::PathBuf;
+use std::str::from_utf8;
+use thiserror::Error as ThisError;
+use tracing::trace;
+
+const HEXADECIMAL_RADIX: u32 = 16;
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Get all items with a key starting with `prefix`.
+    ///
+    /// Only chunks that can contain a matching key are read.
+    pub async fn scan_prefix(
+        &self,
+        prefix: &[u8],
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        let mut items = BTreeMap::new();
+        for chunk_hash in self.list_chunks().await? {
+            if !chunk_matches_prefix(chunk_hash, prefix) {
+                continue;
+            }
+            let chunk = self
+                .load_chunk_shared(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::ScanPrefix))?;
+            items.extend(
+                chunk
+                    .into_iter()
+                    .filter(|(hash, _)| hash.as_bytes().starts_with(prefix)),
+            );
+        }
+        trace!(prefix = ?prefix, count = items.len(), "Scan prefix");
+        Ok(items)
+    }
+
+    /// Get all items with a key in `range`.
+    ///
+    /// Only chunks that can contain a matching key are read.
+    pub async fn range(
+        &self,
+        range: impl RangeBounds<Hash<K>>,
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        let mut items = BTreeMap::new();
+        for chunk_hash in self.list_chunks().await? {
+            if !chunk_in_range(chunk_hash, &range) {
+                continue;
+            }
+            let chunk = self
+                .load_chunk_shared(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Range))?;
+            items.extend(chunk.into_iter().filter(|(hash, _)| range.contains(hash)));
+        }
+        trace!(count = items.len(), "Scan range");
+        Ok(items)
+    }
+
+    /// Get the item with a key starting with the abbreviated hexadecimal `abbrev`.
+    ///
+    /// Returns `None` if no key matches.
+    ///
+    /// Fails if more than one key matches.
+    pub async fn find_by_abbrev(
+        &self,
+        abbrev: &str,
+    ) -> Result<Option<(Hash<K>, T)>, Failure<TableAction>> {
+        let prefix =
+            parse_abbrev::<K>(abbrev).map_err(|e| Failure::new(TableAction::FindByAbbrev, e))?;
+        let items = self
+            .scan_prefix(&prefix)
+            .await
+            .map_err(Failure::wrap(TableAction::FindByAbbrev))?;
+        let mut matches = filter_abbrev(items, abbrev);
+        if matches.len() > 1 {
+            return Err(Failure::new(
+                TableAction::FindByAbbrev,
+                ScanError::Ambiguous {
+                    abbrev: abbrev.to_owned(),
+                    count: matches.len(),
+                },
+            ));
+        }
+        Ok(matches.pop_first())
+    }
+}
+
+impl<const K: usize, const C: usize> FileTable<K, C> {
+    /// Get all file paths with a key starting with `prefix`.
+    ///
+    /// Only chunk directories that can contain a matching key are read.
+    pub async fn scan_prefix(
+        &self,
+        prefix: &[u8],
+    ) -> Result<BTreeMap<Hash<K>, PathBuf>, Failure<FileTableAction>> {
+        let mut paths = BTreeMap::new();
+        for (chunk_hash, dir) in self.list_chunk_dirs().await? {
+            if !chunk_matches_prefix(chunk_hash, prefix) {
+                continue;
+            }
+            let chunk = self.read_chunk_dir(&dir).await?;
+            paths.extend(
+                chunk
+                    .into_iter()
+                    .filter(|(hash, _)| hash.as_bytes().starts_with(prefix)),
+            );
+        }
+        trace!(prefix = ?prefix, count = paths.len(), "Scan prefix");
+        Ok(paths)
+    }
+
+    /// Get all file paths with a key in `range`.
+    ///
+    /// Only chunk directories that can contain a matching key are read.
+    pub async fn range(
+        &self,
+        range: impl RangeBounds<Hash<K>>,
+    ) -> Result<BTreeMap<Hash<K>, PathBuf>, Failure<FileTableAction>> {
+        let mut paths = BTreeMap::new();
+        for (chunk_hash, dir) in self.list_chunk_dirs().await? {
+            if !chunk_in_range(chunk_hash, &range) {
+                continue;
+            }
+            let chunk = self.read_chunk_dir(&dir).await?;
+            paths.extend(chunk.into_iter().filter(|(hash, _)| range.contains(hash)));
+        }
+        trace!(count = paths.len(), "Scan range");
+        Ok(paths)
+    }
+
+    /// Get the file path with a key starting with the abbreviated hexadecimal `abbrev`.
+    ///
+    /// Returns `None` if no key matches.
+    ///
+    /// Fails if more than one key matches.
+    pub async fn find_by_abbrev(
+        &self,
+        abbrev: &str,
+    ) -> Result<Option<(Hash<K>, PathBuf)>, Failure<FileTableAction>> {
+        let prefix = parse_abbrev::<K>(abbrev)
+            .map_err(|e| Failure::new(FileTableAction::FindByAbbrev, e))?;
+        let paths = self
+            .scan_prefix(&prefix)
+            .await
+            .map_err(Failure::wrap(FileTableAction::FindByAbbrev))?;
+        let mut matches = filter_abbrev(paths, abbrev);
+        if matches.len() > 1 {
+            return Err(Failure::new(
+                FileTableAction::FindByAbbrev,
+                ScanError::Ambiguous {
+                    abbrev: abbrev.to_owned(),
+                    count: matches.len(),
+                },
+            ));
+        }
+        Ok(matches.pop_first())
+    }
+}
+
+/// Whether a chunk can contain keys starting with `prefix`.
+fn chunk_matches_prefix<const C: usize>(chunk_hash: Hash<C>, prefix: &[u8]) -> bool {
+    chunk_hash
+        .as_bytes()
+        .iter()
+        .zip(prefix)
+        .all(|(chunk_byte, prefix_byte)| chunk_byte == prefix_byte)
+}
+
+/// Whether a chunk can contain keys in `range`.
+fn chunk_in_range<const K: usize, const C: usize>(
+    chunk_hash: Hash<C>,
+    range: &impl RangeBounds<Hash<K>>,
+) -> bool {
+    let after_start = match range.start_bound() {
+        Bound::Included(start) | Bound::Excluded(start) => {
+            chunk_hash >= get_chunk_hash::<K, C>(*start)
+        }
+        Bound::Unbounded => true,
+    };
+    let before_end = match range.end_bound() {
+        Bound::Included(end) | Bound::Excluded(end) => chunk_hash <= get_chunk_hash::<K, C>(*end),
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
+
+/// Get the whole bytes of an abbreviated hexadecimal hash.
+///
+/// A trailing odd character is matched by [`filter_abbrev`].
+fn parse_abbrev<const K: usize>(abbrev: &str) -> Result<Vec<u8>, ScanError> {
+    let invalid = || ScanError::InvalidAbbrev {
+        abbrev: abbrev.to_owned(),
+    };
+    if abbrev.is_empty()
+        || abbrev.len() > K * 2
+        || !abbrev.chars().all(|char| char.is_ascii_hexdigit())
+    {
+        return Err(invalid());
+    }
+    abbrev
+        .as_bytes()
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair = from_utf8(pair).map_err(|_| invalid())?;
+            u8::from_str_radix(pair, HEXADECIMAL_RADIX).map_err(|_| invalid())
+        })
+        .collect()
+}
+
+/// Keep only the entries with a key starting with `abbrev`.
+fn filter_abbrev<const K: usize, V>(
+    entries: BTreeMap<Hash<K>, V>,
+    abbrev: &str,
+) -> BTreeMap<Hash<K>, V> {
+    let abbrev = abbrev.to_ascii_lowercase();
+    entries
+        .into_iter()
+        .filter(|(hash, _)| hash.to_hex().starts_with(&abbrev))
+        .collect()
+}
+
+/// Errors when scanning a [`Table`] or [`FileTable`] by key.
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum ScanError {
+    #[error("Invalid abbreviated hash: {abbrev}")]
+    InvalidAbbrev { abbrev: String },
+    #[error("Abbreviated hash {abbrev} matches {count} keys")]
+    Ambiguous { abbrev: String, count: usize },
+}
@@ -0,0 +1,236 @@
+use crate::table::get_chunk_hash;
+use crate::{ChunkFormat, Hash, Table};
+use std::fs::Metadata;
+use std::io::{self, ErrorKind};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+use tokio::fs::{create_dir_all, metadata, read, remove_file, rename, write};
+use tracing::{trace, warn};
+
+const SIDECAR_DIR_NAME: &str = ".sidecars";
+const SIDECAR_EXTENSION: &str = "keys";
+/// Length, modification time, inode and change time of the chunk file.
+const HEADER_BYTES: usize = 44;
+
+/// Counter to give each temporary sidecar file a unique name.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Result of looking up an item in the sidecar of its chunk.
+pub(crate) enum SidecarLookup {
+    /// Sidecars are disabled or can't be trusted.
+    Unavailable,
+    /// The sidecar is current and lists the item, or doesn't.
+    Found(bool),
+    /// The sidecar is missing or out of date.
+    ///
+    /// It should be regenerated from the chunk file with this stamp.
+    Stale(ChunkStamp),
+}
+
+/// Identity of a chunk file when its sidecar was written or it was cached.
+///
+/// The inode and change time catch rewrites that keep the length and modification
+/// time, such as a `git checkout` or a file system with coarse timestamps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ChunkStamp {
+    len: u64,
+    secs: u64,
+    nanos: u32,
+    inode: u64,
+    changed_secs: i64,
+    changed_nanos: i64,
+}
+
+impl ChunkStamp {
+    /// Get the stamp of a file.
+    ///
+    /// Returns `None` if the file does not exist or the modification time is not
+    /// available.
+    pub(crate) async fn read(path: &Path) -> Option<Self> {
+        let metadata = metadata(path).await.ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let (inode, changed_secs, changed_nanos) = get_inode_and_change_time(&metadata);
+        Some(Self {
+            len: metadata.len(),
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+            inode,
+            changed_secs,
+            changed_nanos,
+        })
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES);
+        bytes.extend(self.len.to_le_bytes());
+        bytes.extend(self.secs.to_le_bytes());
+        bytes.extend(self.nanos.to_le_bytes());
+        bytes.extend(self.inode.to_le_bytes());
+        bytes.extend(self.changed_secs.to_le_bytes());
+        bytes.extend(self.changed_nanos.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_BYTES]) -> Option<Self> {
+        let (len, rest) = bytes.split_first_chunk::<8>()?;
+        let (secs, rest) = rest.split_first_chunk::<8>()?;
+        let (nanos, rest) = rest.split_first_chunk::<4>()?;
+        let (inode, rest) = rest.split_first_chunk::<8>()?;
+        let (changed_secs, rest) = rest.split_first_chunk::<8>()?;
+        let changed_nanos = rest.first_chunk::<8>()?;
+        Some(Self {
+            len: u64::from_le_bytes(*len),
+            secs: u64::from_le_bytes(*secs),
+            nanos: u32::from_le_bytes(*nanos),
+            inode: u64::from_le_bytes(*inode),
+            changed_secs: i64::from_le_bytes(*changed_secs),
+            changed_nanos: i64::from_le_bytes(*changed_nanos),
+        })
+    }
+}
+
+/// Get the inode and change time of a file.
+#[cfg(unix)]
+fn get_inode_and_change_time(metadata: &Metadata) -> (u64, i64, i64) {
+    (metadata.ino(), metadata.ctime(), metadata.ctime_nsec())
+}
+
+/// Inodes and change times are not available on this platform.
+#[cfg(not(unix))]
+fn get_inode_and_change_time(_metadata: &Metadata) -> (u64, i64, i64) {
+    (0, 0, 0)
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Keep a sorted list of the keys of each chunk in a sidecar file.
+    ///
+    /// - [`Table::get`] and [`Table::contains`] skip parsing the chunk if its sidecar
+    ///   does not list the key
+    /// - Sidecars are written to `.sidecars` in the table directory with each chunk
+    /// - A sidecar is only trusted if the length, modification time, inode and change
+    ///   time of the chunk file match those it recorded, otherwise the next read
+    ///   regenerates it
+    /// - Sidecars are not used while the write-ahead log has entries
+    ///
+    /// Sidecars are regenerated from the chunk files so `.sidecars` can be ignored
+    /// by git.
+    ///
+    /// Default: `false`
+    #[must_use]
+    pub fn with_sidecars(mut self, sidecars: bool) -> Self {
+        self.sidecars = sidecars;
+        self
+    }
+
+    /// Get the path to the sidecar of a chunk.
+    fn get_sidecar_path(&self, hash: Hash<C>) -> PathBuf {
+        self.directory
+            .join(SIDECAR_DIR_NAME)
+            .join(format!("{hash}.{SIDECAR_EXTENSION}"))
+    }
+
+    /// Look up an item in the sidecar of its chunk.
+    pub(crate) async fn lookup_sidecar(&self, hash: Hash<K>) -> SidecarLookup {
+        if !self.sidecars || (self.is_wal_enabled() && self.get_wal_path().is_file()) {
+            return SidecarLookup::Unavailable;
+        }
+        let chunk_hash = get_chunk_hash(hash);
+        let Some(chunk_path) = self.find_chunk_path(chunk_hash) else {
+            return SidecarLookup::Unavailable;
+        };
+        let Some(stamp) = ChunkStamp::read(&chunk_path).await else {
+            return SidecarLookup::Unavailable;
+        };
+        let Ok(bytes) = read(self.get_sidecar_path(chunk_hash)).await else {
+            trace!(chunk = %chunk_hash, "Sidecar missing");
+            return SidecarLookup::Stale(stamp);
+        };
+        let Some((header, keys)) = bytes.split_first_chunk::<HEADER_BYTES>() else {
+            return SidecarLookup::Stale(stamp);
+        };
+        let keys = keys.chunks_exact(K);
+        if ChunkStamp::from_bytes(header) != Some(stamp) || !keys.remainder().is_empty() {
+            trace!(chunk = %chunk_hash, "Sidecar out of date");
+            return SidecarLookup::Stale(stamp);
+        }
+        let keys: Vec<&[u8]> = keys.collect();
+        let found = keys.binary_search(&hash.as_bytes().as_slice()).is_ok();
+        trace!(hash = %hash, found, "Checked sidecar");
+        SidecarLookup::Found(found)
+    }
+
+    /// Write the sidecar of a chunk file.
+    ///
+    /// Failures are logged rather than returned as the next read regenerates it.
+    pub(crate) async fn write_sidecar<'a>(
+        &self,
+        hash: Hash<C>,
+        stamp: ChunkStamp,
+        keys: impl IntoIterator<Item = &'a Hash<K>>,
+    ) {
+        let mut bytes = stamp.to_bytes();
+        for key in keys {
+            bytes.extend(key.as_bytes());
+        }
+        let path = self.get_sidecar_path(hash);
+        if let Err(error) = write_sidecar_file(&path, bytes).await {
+            warn!(path = %path.display(), %error, "Failed to write sidecar");
+        } else {
+            trace!(path = %path.display(), "Wrote sidecar");
+        }
+    }
+
+    /// Write the sidecar of a chunk file that was just written.
+    pub(crate) async fn write_sidecar_for<'a>(
+        &self,
+        hash: Hash<C>,
+        chunk_path: &Path,
+        keys: impl IntoIterator<Item = &'a Hash<K>>,
+    ) {
+        if !self.sidecars {
+            return;
+        }
+        if let Some(stamp) = ChunkStamp::read(chunk_path).await {
+            self.write_sidecar(hash, stamp, keys).await;
+        }
+    }
+
+    /// Remove the sidecar of a chunk.
+    pub(crate) async fn remove_sidecar(&self, hash: Hash<C>) {
+        if !self.sidecars {
+            return;
+        }
+        let path = self.get_sidecar_path(hash);
+        match remove_file(&path).await {
+            Ok(()) => trace!(path = %path.display(), "Removed sidecar"),
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => warn!(path = %path.display(), %error, "Failed to remove sidecar"),
+        }
+    }
+}
+
+/// Write a sidecar to a uniquely named temporary file then rename it into place.
+///
+/// Sidecars are regenerated if lost so, unlike chunks, they are not synced.
+async fn write_sidecar_file(path: &Path, bytes: Vec<u8>) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).await?;
+    }
+    let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temp_path = path.with_extension(format!(
+        "{SIDECAR_EXTENSION}.{}-{counter}.tmp",
+        process::id()
+    ));
+    let result = match write(&temp_path, bytes).await {
+        Ok(()) => rename(&temp_path, path).await,
+        Err(error) => Err(error),
+    };
+    if result.is_err() {
+        let _ = remove_file(&temp_path).await;
+    }
+    result
+}
@@ -0,0 +1,178 @@
+use crate::{ChunkFormat, FileTable, FileTableAction, Hash, Table, TableAction};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use rogue_logging::Failure;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs::read_dir;
+use tracing::trace;
+
+/// Default number of chunks read ahead of the consumer of a stream.
+pub const DEFAULT_READ_AHEAD: usize = 2;
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Set the number of chunks read ahead of the consumer of a stream.
+    ///
+    /// Memory use of [`Table::stream`] is bounded by this many chunks.
+    ///
+    /// Default: [`DEFAULT_READ_AHEAD`]
+    #[must_use]
+    pub fn with_read_ahead(mut self, chunks: usize) -> Self {
+        self.read_ahead = chunks.max(1);
+        self
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Stream every chunk.
+    ///
+    /// Chunks are read one at a time in hash order with bounded read-ahead.
+    pub fn chunks(
+        &self,
+    ) -> impl Stream<Item = Result<(Hash<C>, BTreeMap<Hash<K>, T>), Failure<TableAction>>> + '_
+    {
+        stream::once(self.list_chunks())
+            .map_ok(|chunks| stream::iter(chunks).map(Ok))
+            .try_flatten()
+            .map(move |result| async move {
+                let chunk_hash = result?;
+                let chunk = self
+                    .load_chunk_shared(chunk_hash)
+                    .await
+                    .map_err(Failure::wrap(TableAction::Stream))?;
+                trace!(chunk = %chunk_hash, count = chunk.len(), "Stream chunk");
+                Ok((chunk_hash, chunk))
+            })
+            .buffered(self.read_ahead)
+    }
+
+    /// Stream every item.
+    ///
+    /// Unlike [`Table::get_all`] only the chunks being read are held in memory.
+    pub fn stream(&self) -> impl Stream<Item = Result<(Hash<K>, T), Failure<TableAction>>> + '_ {
+        self.chunks()
+            .map_ok(|(_, chunk)| stream::iter(chunk).map(Ok))
+            .try_flatten()
+    }
+
+    /// Stream the hash of every item.
+    ///
+    /// Only the keys of each chunk are deserialized.
+    pub fn keys(&self) -> impl Stream<Item = Result<Hash<K>, Failure<TableAction>>> + '_ {
+        stream::once(self.list_chunks())
+            .map_ok(|chunks| stream::iter(chunks).map(Ok))
+            .try_flatten()
+            .map(move |result| async move {
+                let chunk_hash = result?;
+                let keys = self
+                    .load_chunk_keys(chunk_hash)
+                    .await
+                    .map_err(Failure::wrap(TableAction::Stream))?;
+                trace!(chunk = %chunk_hash, count = keys.len(), "Stream chunk keys");
+                Ok(keys)
+            })
+            .buffered(self.read_ahead)
+            .map_ok(|keys| stream::iter(keys).map(Ok))
+            .try_flatten()
+    }
+}
+
+impl<const K: usize, const C: usize> FileTable<K, C> {
+    /// Set the number of chunk directories read ahead of the consumer of a stream.
+    ///
+    /// Default: [`DEFAULT_READ_AHEAD`]
+    #[must_use]
+    pub fn with_read_ahead(mut self, chunks: usize) -> Self {
+        self.read_ahead = chunks.max(1);
+        self
+    }
+
+    /// Stream every file path.
+    ///
+    /// Chunk directories are read one at a time with bounded read-ahead.
+    pub fn stream(
+        &self,
+    ) -> impl Stream<Item = Result<(Hash<K>, PathBuf), Failure<FileTableAction>>> + '_ {
+        stream::once(self.list_chunk_dirs())
+            .map_ok(|dirs| stream::iter(dirs).map(Ok))
+            .try_flatten()
+            .map(move |result| async move {
+                let (_, path) = result?;
+                self.read_chunk_dir(&path).await
+            })
+            .buffered(self.read_ahead)
+            .map_ok(|paths| stream::iter(paths).map(Ok))
+            .try_flatten()
+    }
+
+    /// Get the hash and path of every chunk directory.
+    pub(crate) async fn list_chunk_dirs(
+        &self,
+    ) -> Result<Vec<(Hash<C>, PathBuf)>, Failure<FileTableAction>> {
+        let mut dirs = Vec::new();
+        let mut parent_dir = read_dir(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(
+                FileTableAction::ReadDir,
+                &self.directory,
+            ))?;
+        while let Some(entry) = parent_dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(FileTableAction::ReadEntry))?
+        {
+            let path = entry.path();
+            let chunk_hash = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| Hash::<C>::from_string(name).ok());
+            match chunk_hash {
+                Some(chunk_hash) if path.is_dir() => dirs.push((chunk_hash, path)),
+                _ => trace!("Skipping non-chunk directory: {}", path.display()),
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    /// Get the hash and path of every file in a chunk directory.
+    pub(crate) async fn read_chunk_dir(
+        &self,
+        path: &Path,
+    ) -> Result<Vec<(Hash<K>, PathBuf)>, Failure<FileTableAction>> {
+        let mut paths = Vec::new();
+        let mut chunk_dir = read_dir(path)
+            .await
+            .map_err(Failure::wrap_with_path(FileTableAction::ReadChunkDir, path))?;
+        while let Some(entry) = chunk_dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(FileTableAction::ReadChunkEntry))?
+        {
+            let path = entry.path();
+            let extension = path
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if !path.is_file() || extension != self.extension {
+                trace!("Skipping non-chunk file: {}", path.display());
+                continue;
+            }
+            let Some(stem) = path.file_stem() else {
+                trace!("File does not have a stem: {}", path.display());
+                continue;
+            };
+            let Ok(hash) = Hash::from_string(stem.to_string_lossy().as_ref()) else {
+                trace!("File stem is not a hash: {}", path.display());
+                continue;
+            };
+            paths.push((hash, path));
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
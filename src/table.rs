@@ -1,47 +1,403 @@
-use crate::Hash;
-use crate::lock_guard::acquire_lock;
-use futures::future;
+use crate::atomic_file::write_atomic;
+use crate::cache::ChunkCache;
+use crate::index::Indexes;
+use crate::lock_guard::{LockGuard, LockOptions, acquire_lock};
+use crate::sidecar::SidecarLookup;
+use crate::wal::{WalTail, merge_wal};
+use crate::{ChunkFormat, Compression, DEFAULT_READ_AHEAD, Hash, Migrations, Yaml};
+use futures::stream::{self, StreamExt};
 use rogue_logging::Failure;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 use thiserror::Error as ThisError;
-use tokio::fs::{read, read_dir, write};
+use tokio::fs::{read, read_dir, remove_file, write};
 use tokio::task;
 use tracing::{debug, trace};
 
-const CHUNK_FILE_EXTENSION: &str = "yml";
+/// Default maximum number of chunks or files read or written at once by batch
+/// operations.
+pub const DEFAULT_CONCURRENCY: usize = 32;
 
 /// Key-value table with chunked file storage.
 ///
 /// - Items of type `T` are stored by key of type `Hash<K>`
 /// - Get and set operations are performed directly on the file system
 /// - Chunks are determined by truncating the key to a `Hash<C>`
-/// - All items in a chunk are serialized to a single YAML file
+/// - All items in a chunk are serialized to a single file in format `F`
+/// - Chunk files are optionally compressed
 /// - Write operations are protected by lock files
-pub struct Table<const K: usize, const C: usize, T> {
+/// - Chunks are written atomically so a crash never leaves a partial chunk
+pub struct Table<const K: usize, const C: usize, T, F = Yaml> {
     /// Directory for storing the data.
     pub(crate) directory: PathBuf,
+    /// Serialization format of chunk files.
+    pub(crate) format: F,
+    /// Compression applied when writing chunk files.
+    pub(crate) compression: Compression,
+    /// Name of the item schema recorded in the manifest.
+    ///
+    /// Empty if not set with [`Table::with_schema`].
+    pub(crate) schema: String,
+    /// Version of the item schema recorded in the manifest.
+    pub(crate) version: u32,
+    /// Steps to upgrade items written with an older schema version.
+    pub(crate) migrations: Migrations,
+    /// Whether the manifest has reached `version` so chunks are no longer checked
+    /// for migration.
+    pub(crate) is_migrated: Arc<AtomicBool>,
+    /// Secondary indexes maintained when items are written.
+    pub(crate) indexes: Indexes<T>,
+    /// Number of chunks read ahead when streaming.
+    pub(crate) read_ahead: usize,
+    /// Maximum number of chunks read or written at once by batch operations.
+    pub(crate) concurrency: usize,
+    /// Size of the write-ahead log that triggers a checkpoint, if enabled.
+    pub(crate) wal_checkpoint_bytes: Option<u64>,
+    /// Last record appended to the write-ahead log by this table or its clones.
+    pub(crate) wal_tail: Arc<Mutex<Option<WalTail>>>,
+    /// Cache of deserialized chunks, if enabled.
+    pub(crate) cache: Option<ChunkCache<K, C, T>>,
+    /// Options for acquiring lock files.
+    pub(crate) lock_options: LockOptions,
+    /// Whether chunks are written to a temporary file then renamed.
+    pub(crate) atomic_writes: bool,
+    /// Whether a sorted key list is kept beside each chunk.
+    pub(crate) sidecars: bool,
+    /// Whether lenient reads move unreadable chunks and items into quarantine.
+    pub(crate) quarantine: bool,
+    /// Table-wide lock held by [`Table::exclusive`], if any.
+    pub(crate) exclusive_lock: Option<Arc<LockGuard>>,
     /// Marker for the item type.
     pub phantom: PhantomData<T>,
 }
 
 impl<const K: usize, const C: usize, T> Table<K, C, T> {
-    /// Create a new [`Table`]
+    /// Create a new [`Table`] using the default [`Yaml`] format.
     #[must_use]
     pub fn new(directory: impl Into<PathBuf>) -> Self {
         Self {
             directory: directory.into(),
+            format: Yaml,
+            compression: Compression::None,
+            schema: String::new(),
+            version: 0,
+            migrations: Migrations::new(),
+            is_migrated: Arc::new(AtomicBool::new(false)),
+            indexes: Indexes::new(),
+            read_ahead: DEFAULT_READ_AHEAD,
+            concurrency: DEFAULT_CONCURRENCY,
+            wal_checkpoint_bytes: None,
+            wal_tail: Arc::new(Mutex::new(None)),
+            cache: None,
+            lock_options: LockOptions::default(),
+            atomic_writes: true,
+            sidecars: false,
+            quarantine: false,
+            exclusive_lock: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Use a different serialization format for chunk files.
+    #[must_use]
+    pub fn with_format<F2: ChunkFormat>(self, format: F2) -> Table<K, C, T, F2> {
+        Table {
+            directory: self.directory,
+            format,
+            compression: self.compression,
+            schema: self.schema,
+            version: self.version,
+            migrations: self.migrations,
+            is_migrated: self.is_migrated,
+            indexes: self.indexes,
+            read_ahead: self.read_ahead,
+            concurrency: self.concurrency,
+            wal_checkpoint_bytes: self.wal_checkpoint_bytes,
+            wal_tail: self.wal_tail,
+            cache: self.cache,
+            lock_options: self.lock_options,
+            atomic_writes: self.atomic_writes,
+            sidecars: self.sidecars,
+            quarantine: self.quarantine,
+            exclusive_lock: self.exclusive_lock,
             phantom: PhantomData,
         }
     }
 
-    /// Get the path to the chunk file.
-    fn get_chunk_path(&self, hash: Hash<C>) -> PathBuf {
+    /// Compress chunk files when they are written.
+    ///
+    /// Chunks with any other compression, or none, are still read so a table can be
+    /// migrated gradually. Each chunk is converted the next time it is written.
+    #[must_use]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Write chunks to a temporary file then rename it over the existing chunk.
+    ///
+    /// Disabling atomic writes avoids the extra sync and rename but readers must then
+    /// take a shared lock to avoid observing a partial write.
+    ///
+    /// Default: `true`
+    #[must_use]
+    pub fn with_atomic_writes(mut self, atomic_writes: bool) -> Self {
+        self.atomic_writes = atomic_writes;
+        self
+    }
+
+    /// Set the maximum number of chunks read or written at once by [`Table::get_many`],
+    /// [`Table::set_many`] and [`Table::remove_many`].
+    ///
+    /// Each chunk being written holds a lock file and a chunk file open.
+    ///
+    /// Default: [`DEFAULT_CONCURRENCY`]
+    #[must_use]
+    pub fn with_concurrency(mut self, chunks: usize) -> Self {
+        self.concurrency = chunks.max(1);
+        self
+    }
+
+    /// Get the path to the uncompressed chunk file.
+    ///
+    /// Lock files are derived from this path so they are shared by every compression.
+    pub(crate) fn get_base_chunk_path(&self, hash: Hash<C>) -> PathBuf {
         self.directory
-            .join(format!("{hash}.{CHUNK_FILE_EXTENSION}"))
+            .join(format!("{hash}.{}", self.format.extension()))
+    }
+
+    /// Get the path to the chunk file with the configured compression.
+    pub(crate) fn get_chunk_path(&self, hash: Hash<C>) -> PathBuf {
+        self.compression
+            .apply_to_path(self.get_base_chunk_path(hash))
+    }
+
+    /// Get the paths of every existing variant of the chunk file.
+    ///
+    /// The configured compression is first.
+    pub(crate) fn find_chunk_paths(&self, hash: Hash<C>) -> Vec<PathBuf> {
+        let base = self.get_base_chunk_path(hash);
+        let preferred = self.compression;
+        let others = Compression::ALL
+            .iter()
+            .copied()
+            .filter(|compression| *compression != preferred);
+        [preferred]
+            .into_iter()
+            .chain(others)
+            .map(|compression| compression.apply_to_path(base.clone()))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Get the path to the existing chunk file.
+    ///
+    /// Returns `None` if the chunk does not exist.
+    pub(crate) fn find_chunk_path(&self, hash: Hash<C>) -> Option<PathBuf> {
+        self.find_chunk_paths(hash).into_iter().next()
+    }
+
+    /// Get the chunk hash from the path of a chunk file.
+    ///
+    /// Returns `None` if the path is not a chunk file of this table.
+    pub(crate) fn parse_chunk_path(&self, path: &Path) -> Option<Hash<C>> {
+        let path = Compression::from_path(path).strip_from_path(path);
+        if path.extension()? != self.format.extension() {
+            return None;
+        }
+        Hash::from_string(path.file_stem()?.to_str()?).ok()
+    }
+
+    /// Get the hashes of all chunks in the directory.
+    pub(crate) async fn list_chunks(&self) -> Result<BTreeSet<Hash<C>>, Failure<TableAction>> {
+        let mut chunks = BTreeSet::new();
+        let dir_path = self.directory.clone();
+        let mut dir = read_dir(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadDir, &dir_path))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            let path = entry.path();
+            let chunk_hash = self.parse_chunk_path(&path);
+            match chunk_hash {
+                Some(chunk_hash) if path.is_file() => {
+                    chunks.insert(chunk_hash);
+                }
+                _ => trace!("Skipping non-chunk file: {}", path.display()),
+            }
+        }
+        chunks.extend(self.list_wal_chunks().await?);
+        Ok(chunks)
+    }
+
+    /// Acquire the lock for a chunk.
+    pub(crate) async fn lock_chunk(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<LockGuard, Failure<TableAction>> {
+        acquire_lock(self.get_base_chunk_path(hash), &self.lock_options).await
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Read a chunk from a file.
+    pub(crate) async fn read_chunk(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        let path = path.as_ref();
+        debug!(path = %path.display(), "Reading chunk");
+        let bytes = read(path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadChunk, path))?;
+        let bytes = Compression::from_path(path)
+            .decompress(bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Decompress, path))?;
+        if let Some(version) = self.get_pending_version(path).await? {
+            return self.migrate_chunk(path, &bytes, version);
+        }
+        self.format
+            .deserialize(&bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Deserialize, path))
+    }
+
+    /// Read a chunk by hash, including entries in the write-ahead log.
+    ///
+    /// The log is read first so a concurrent checkpoint can't remove entries that
+    /// are not yet in the chunk file.
+    ///
+    /// Returns an empty chunk if the chunk does not exist.
+    pub(crate) async fn load_chunk(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        let entries = self.read_wal_chunk(hash).await?;
+        let mut chunk = match self.find_chunk_path(hash) {
+            Some(path) => self.read_chunk(path).await?,
+            None => BTreeMap::new(),
+        };
+        merge_wal(&mut chunk, entries);
+        Ok(chunk)
+    }
+
+    /// Read a chunk by hash while holding a shared lock if reads require one.
+    ///
+    /// See [`Table::load_chunk`].
+    pub(crate) async fn load_chunk_shared(
+        &self,
+        hash: Hash<C>,
+    ) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
+        let _lock = self.lock_chunk_shared(hash).await?;
+        self.load_chunk(hash).await
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Serialize,
+{
+    /// Write a chunk to a file
+    ///
+    /// If atomic writes are enabled the chunk is written to a temporary file then
+    /// renamed over the existing chunk.
+    ///
+    /// Variants of the chunk with a different compression are removed.
+    pub(crate) async fn write_chunk(
+        &self,
+        hash: Hash<C>,
+        chunk: BTreeMap<Hash<K>, T>,
+    ) -> Result<(), Failure<TableAction>> {
+        let (path, bytes) = self.encode_chunk(hash, &chunk)?;
+        debug!(path = %path.display(), "Writing chunk");
+        if self.atomic_writes {
+            write_atomic(&path, bytes).await
+        } else {
+            write(&path, bytes).await
+        }
+        .map_err(Failure::wrap_with_path(TableAction::WriteChunk, &path))?;
+        self.complete_chunk_write(hash, &path).await?;
+        self.write_sidecar_for(hash, &path, chunk.keys()).await;
+        Ok(())
+    }
+
+    /// Serialize and compress a chunk.
+    ///
+    /// Returns the path the chunk should be written to and its contents.
+    pub(crate) fn encode_chunk(
+        &self,
+        hash: Hash<C>,
+        chunk: &BTreeMap<Hash<K>, T>,
+    ) -> Result<(PathBuf, Vec<u8>), Failure<TableAction>> {
+        let path = self.get_chunk_path(hash);
+        let bytes = self
+            .format
+            .serialize(chunk)
+            .map_err(Failure::wrap_with_path(TableAction::Serialize, &path))?;
+        let bytes = self
+            .compression
+            .compress(bytes)
+            .map_err(Failure::wrap_with_path(TableAction::Compress, &path))?;
+        Ok((path, bytes))
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Remove variants of a chunk other than `path` and record its schema version.
+    pub(crate) async fn complete_chunk_write(
+        &self,
+        hash: Hash<C>,
+        path: &Path,
+    ) -> Result<(), Failure<TableAction>> {
+        self.invalidate_cached_chunk(hash);
+        self.remove_sidecar(hash).await;
+        for other in self.find_chunk_paths(hash) {
+            if other != path {
+                trace!(path = %other.display(), "Removing chunk with other compression");
+                remove_file(&other)
+                    .await
+                    .map_err(Failure::wrap_with_path(TableAction::RemoveChunk, &other))?;
+            }
+        }
+        self.record_chunk_version(hash).await
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: Clone> Clone for Table<K, C, T, F> {
+    fn clone(&self) -> Self {
+        Self {
+            directory: self.directory.clone(),
+            format: self.format.clone(),
+            compression: self.compression,
+            schema: self.schema.clone(),
+            version: self.version,
+            migrations: self.migrations.clone(),
+            is_migrated: self.is_migrated.clone(),
+            indexes: self.indexes.clone(),
+            read_ahead: self.read_ahead,
+            concurrency: self.concurrency,
+            wal_checkpoint_bytes: self.wal_checkpoint_bytes,
+            wal_tail: self.wal_tail.clone(),
+            cache: self.cache.clone(),
+            lock_options: self.lock_options,
+            atomic_writes: self.atomic_writes,
+            sidecars: self.sidecars,
+            quarantine: self.quarantine,
+            exclusive_lock: self.exclusive_lock.clone(),
+            phantom: PhantomData,
+        }
     }
 }
 
@@ -51,7 +407,7 @@ impl<const K: usize, const C: usize, T> Default for Table<K, C, T> {
     }
 }
 
-impl<const K: usize, const C: usize, T> Table<K, C, T>
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
 where
     T: Clone + DeserializeOwned,
 {
@@ -59,18 +415,22 @@ where
     ///
     /// Returns `None` if the item is not found.
     pub async fn get(&self, hash: Hash<K>) -> Result<Option<T>, Failure<TableAction>> {
-        let chunk_path = self.get_chunk_path(get_chunk_hash(hash));
-        if chunk_path.exists() {
-            let chunk = read_chunk::<K, C, T>(&chunk_path)
-                .await
-                .map_err(Failure::wrap(TableAction::Get))?;
-            let item = chunk.get(&hash).cloned();
-            trace!(hash = %hash, found = item.is_some(), "Get item");
-            Ok(item)
-        } else {
+        let chunk_hash = get_chunk_hash(hash);
+        let lookup = self.lookup_sidecar(hash).await;
+        if let SidecarLookup::Found(false) = lookup {
             trace!(hash = %hash, found = false, "Get item");
-            Ok(None)
+            return Ok(None);
         }
+        let chunk = self
+            .load_chunk_cached(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::Get))?;
+        if let SidecarLookup::Stale(stamp) = lookup {
+            self.write_sidecar(chunk_hash, stamp, chunk.keys()).await;
+        }
+        let item = chunk.get(&hash).cloned();
+        trace!(hash = %hash, found = item.is_some(), "Get item");
+        Ok(item)
     }
 
     /// Get all items.
@@ -78,26 +438,9 @@ where
     /// Items are unsorted.
     pub async fn get_all(&self) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>> {
         let mut items = BTreeMap::new();
-        let dir_path = self.directory.clone();
-        let mut dir = read_dir(&self.directory)
-            .await
-            .map_err(Failure::wrap_with_path(TableAction::ReadDir, &dir_path))?;
-        while let Some(entry) = dir
-            .next_entry()
-            .await
-            .map_err(Failure::wrap(TableAction::ReadEntry))?
-        {
-            let path = entry.path();
-            let extension = path
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-            if !path.is_file() || extension != CHUNK_FILE_EXTENSION {
-                trace!("Skipping non-chunk file: {}", path.display());
-                continue;
-            }
-            let chunk = read_chunk::<K, C, T>(&path)
+        for chunk_hash in self.list_chunks().await? {
+            let chunk = self
+                .load_chunk_shared(chunk_hash)
                 .await
                 .map_err(Failure::wrap(TableAction::GetAll))?;
             items.extend(chunk);
@@ -105,38 +448,59 @@ where
         trace!(count = items.len(), "Get all items");
         Ok(items)
     }
+
+    /// Get many items by hash.
+    ///
+    /// Each chunk is read once. At most [`Table::with_concurrency`] chunks are read
+    /// at once.
+    ///
+    /// Returns every requested hash with `None` if the item is not found.
+    pub async fn get_many(
+        &self,
+        hashes: impl IntoIterator<Item = Hash<K>>,
+    ) -> Result<BTreeMap<Hash<K>, Option<T>>, Failure<TableAction>> {
+        let chunks = group_by_chunk(hashes.into_iter().map(|hash| (hash, ())).collect());
+        trace!(chunks = chunks.len(), "Get many items");
+        let results: Vec<_> = stream::iter(chunks)
+            .map(|(chunk_hash, hashes)| async move {
+                let chunk = self.load_chunk_cached(chunk_hash).await?;
+                let items: Vec<_> = hashes
+                    .into_keys()
+                    .map(|hash| (hash, chunk.get(&hash).cloned()))
+                    .collect();
+                Ok::<_, Failure<TableAction>>(items)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        let mut items = BTreeMap::new();
+        for result in results {
+            items.extend(result.map_err(Failure::wrap(TableAction::GetMany))?);
+        }
+        let found = items.values().filter(|item| item.is_some()).count();
+        trace!(count = items.len(), found, "Get many items complete");
+        Ok(items)
+    }
 }
 
-impl<const K: usize, const C: usize, T> Table<K, C, T>
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
 where
-    T: Clone + Send + Serialize + DeserializeOwned + 'static,
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
 {
     /// Add or replace an item.
     pub async fn set(&self, hash: Hash<K>, item: T) -> Result<(), Failure<TableAction>> {
         trace!(hash = %hash, "Set item");
-        let chunk_path = self.get_chunk_path(get_chunk_hash(hash));
-        let _lock = acquire_lock(&chunk_path)
-            .await
-            .map_err(Failure::wrap(TableAction::Set))?;
-        let mut chunk = if chunk_path.exists() {
-            read_chunk::<K, C, T>(&chunk_path)
-                .await
-                .map_err(Failure::wrap(TableAction::Set))?
-        } else {
-            BTreeMap::new()
-        };
-        chunk.insert(hash, item.clone());
-        write_chunk::<K, C, T>(&chunk_path, chunk)
+        self.replace(hash, Some(item))
             .await
-            .map_err(Failure::wrap(TableAction::Set))?;
-        Ok(())
+            .map_err(Failure::wrap(TableAction::Set))
     }
 
     /// Add many items.
     ///
     /// If `replace` is true then existing items are replaced
     ///
-    /// Items are chunked together to minimize IO operations.
+    /// Items are chunked together to minimize IO operations. At most
+    /// [`Table::with_concurrency`] chunks are written at once.
     ///
     /// Returns the number of items added
     pub async fn set_many(
@@ -144,6 +508,14 @@ where
         items: BTreeMap<Hash<K>, T>,
         replace: bool,
     ) -> Result<usize, Failure<TableAction>> {
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::SetMany))?;
+        let (_, _wal) = self
+            .checkpoint_and_lock_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::SetMany))?;
         let item_count = items.len();
         let chunks = group_by_chunk(items);
         let chunk_count = chunks.len();
@@ -153,13 +525,14 @@ where
             replace,
             "Set many items"
         );
-        let futures = chunks.into_iter().map(|(chunk_hash, new_chunk)| {
-            let chunk_path = self.get_chunk_path(chunk_hash);
-            task::spawn(
-                async move { update_chunk::<K, C, T>(chunk_path, new_chunk, replace).await },
-            )
-        });
-        let results = future::join_all(futures).await;
+        let results: Vec<_> = stream::iter(chunks)
+            .map(|(chunk_hash, new_chunk)| {
+                let table = self.clone();
+                task::spawn(async move { table.update_chunk(chunk_hash, new_chunk, replace).await })
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
         let mut added = 0;
         let mut errors = Vec::new();
         for result in results {
@@ -188,34 +561,206 @@ where
 
     /// Remove an item.
     pub async fn remove(&self, hash: Hash<K>) -> Result<Option<T>, Failure<TableAction>> {
-        let chunk_path = self.get_chunk_path(get_chunk_hash(hash));
-        let _lock = acquire_lock(&chunk_path)
+        let (item, _) = self
+            .modify(hash, |_| Ok(None))
             .await
             .map_err(Failure::wrap(TableAction::Remove))?;
-        let mut chunk = if chunk_path.exists() {
-            read_chunk::<K, C, T>(&chunk_path)
-                .await
-                .map_err(Failure::wrap(TableAction::Remove))?
+        trace!(hash = %hash, found = item.is_some(), "Remove item");
+        Ok(item)
+    }
+
+    /// Remove many items.
+    ///
+    /// Items are chunked together to minimize IO operations. At most
+    /// [`Table::with_concurrency`] chunks are written at once.
+    ///
+    /// Returns the number of items removed
+    pub async fn remove_many(
+        &self,
+        hashes: impl IntoIterator<Item = Hash<K>>,
+    ) -> Result<usize, Failure<TableAction>> {
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::RemoveMany))?;
+        let (_, _wal) = self
+            .checkpoint_and_lock_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::RemoveMany))?;
+        let chunks = group_by_chunk(hashes.into_iter().map(|hash| (hash, ())).collect());
+        let chunk_count = chunks.len();
+        trace!(chunks = chunk_count, "Remove many items");
+        let results: Vec<_> = stream::iter(chunks)
+            .map(|(chunk_hash, hashes)| {
+                let table = self.clone();
+                let hashes = hashes.into_keys().collect();
+                task::spawn(async move { table.remove_from_chunk(chunk_hash, hashes).await })
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        let mut removed = 0;
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(Ok(count)) => removed += count,
+                Ok(Err(e)) => errors.push(e),
+                Err(source) => errors.push(Failure::new(TableAction::JoinTask, source)),
+            }
+        }
+        if errors.is_empty() {
+            trace!(removed, "Remove many items complete");
+            Ok(removed)
         } else {
-            BTreeMap::new()
+            let succeeded = chunk_count - errors.len();
+            let failed = errors.len();
+            trace!(succeeded, failed, "Remove many items complete");
+            let mut failure = Failure::from_action(TableAction::RemoveMany)
+                .with("succeeded", succeeded.to_string())
+                .with("failed", failed.to_string());
+            for error in errors {
+                failure = failure.with_related(error);
+            }
+            Err(failure)
+        }
+    }
+
+    /// Replace an item with the result of `modify` while holding the lock.
+    ///
+    /// - `modify` receives the current item and returns the new item
+    /// - `None` removes the item
+    /// - Nothing is written if the item is absent before and after
+    ///
+    /// Returns the previous and new item
+    pub(crate) async fn modify<M>(
+        &self,
+        hash: Hash<K>,
+        modify: M,
+    ) -> Result<(Option<T>, Option<T>), Failure<TableAction>>
+    where
+        M: FnOnce(Option<T>) -> Result<Option<T>, Failure<TableAction>>,
+    {
+        let _writer = self.lock_writer().await?;
+        if self.is_wal_enabled() {
+            return self.append_wal(hash, modify).await;
+        }
+        let chunk_hash = get_chunk_hash(hash);
+        let _lock = self.lock_chunk(chunk_hash).await?;
+        let mut chunk = self.load_chunk(chunk_hash).await?;
+        let old = chunk.get(&hash).cloned();
+        let new = modify(old.clone())?;
+        if old.is_none() && new.is_none() {
+            return Ok((old, new));
+        }
+        let changes = self.indexes.changes(hash, old.as_ref(), new.as_ref());
+        match &new {
+            Some(item) => chunk.insert(hash, item.clone()),
+            None => chunk.remove(&hash),
         };
-        let item = chunk.remove(&hash);
-        if item.is_some() {
-            write_chunk::<K, C, T>(&chunk_path, chunk)
-                .await
-                .map_err(Failure::wrap(TableAction::Remove))?;
+        self.write_chunk(chunk_hash, chunk).await?;
+        self.apply_index_changes(changes).await?;
+        Ok((old, new))
+    }
+
+    /// Replace an item, or remove it if `None`, without returning the previous item.
+    ///
+    /// The write-ahead log skips reading the previous item unless a secondary index
+    /// needs it.
+    ///
+    /// See [`Table::modify`].
+    pub(crate) async fn replace(
+        &self,
+        hash: Hash<K>,
+        item: Option<T>,
+    ) -> Result<(), Failure<TableAction>> {
+        if self.is_wal_enabled() && self.indexes.is_empty() {
+            let _writer = self.lock_writer().await?;
+            return self.replace_wal(hash, item).await;
         }
-        trace!(hash = %hash, found = item.is_some(), "Remove item");
-        Ok(item)
+        self.modify(hash, |_| Ok(item)).await?;
+        Ok(())
+    }
+
+    /// Update the items in a chunk
+    ///
+    /// If `replace` is true then existing items are replaced
+    async fn update_chunk(
+        &self,
+        chunk_hash: Hash<C>,
+        new_chunk: BTreeMap<Hash<K>, T>,
+        replace: bool,
+    ) -> Result<usize, Failure<TableAction>> {
+        let mut added = 0;
+        let _lock = self
+            .lock_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut chunk = self
+            .load_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut changes = Vec::new();
+        for (hash, item) in new_chunk {
+            if replace || !chunk.contains_key(&hash) {
+                changes.extend(self.indexes.changes(hash, chunk.get(&hash), Some(&item)));
+                chunk.insert(hash, item);
+                added += 1;
+            }
+        }
+        self.write_chunk(chunk_hash, chunk)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        self.apply_index_changes(changes)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        Ok(added)
+    }
+
+    /// Remove items from a chunk
+    ///
+    /// Nothing is written if none of the items are in the chunk.
+    ///
+    /// Returns the number of items removed
+    async fn remove_from_chunk(
+        &self,
+        chunk_hash: Hash<C>,
+        hashes: BTreeSet<Hash<K>>,
+    ) -> Result<usize, Failure<TableAction>> {
+        let _lock = self
+            .lock_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut chunk = self
+            .load_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut removed = 0;
+        let mut changes = Vec::new();
+        for hash in hashes {
+            if let Some(item) = chunk.remove(&hash) {
+                changes.extend(self.indexes.changes(hash, Some(&item), None));
+                removed += 1;
+            }
+        }
+        if removed == 0 {
+            return Ok(0);
+        }
+        self.write_chunk(chunk_hash, chunk)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        self.apply_index_changes(changes)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        Ok(removed)
     }
 }
 
 /// Get the chunk hash from [`hash`]
-fn get_chunk_hash<const K: usize, const C: usize>(hash: Hash<K>) -> Hash<C> {
+pub(crate) fn get_chunk_hash<const K: usize, const C: usize>(hash: Hash<K>) -> Hash<C> {
     hash.truncate::<C>().expect("should be able to truncate")
 }
 
-fn group_by_chunk<const K: usize, const C: usize, T>(
+pub(crate) fn group_by_chunk<const K: usize, const C: usize, T>(
     items: BTreeMap<Hash<K>, T>,
 ) -> BTreeMap<Hash<C>, BTreeMap<Hash<K>, T>> {
     let mut chunks: BTreeMap<Hash<C>, BTreeMap<Hash<K>, T>> = BTreeMap::new();
@@ -230,74 +775,6 @@ fn group_by_chunk<const K: usize, const C: usize, T>(
     chunks
 }
 
-/// Read a chunk from a file.
-async fn read_chunk<const K: usize, const C: usize, T>(
-    path: impl AsRef<Path>,
-) -> Result<BTreeMap<Hash<K>, T>, Failure<TableAction>>
-where
-    T: DeserializeOwned,
-{
-    let path = path.as_ref();
-    debug!(path = %path.display(), "Reading chunk");
-    let bytes = read(path)
-        .await
-        .map_err(Failure::wrap_with_path(TableAction::ReadChunk, path))?;
-    serde_yaml::from_slice(&bytes).map_err(Failure::wrap_with_path(TableAction::Deserialize, path))
-}
-
-/// Write a chunk to a file
-async fn write_chunk<const K: usize, const C: usize, T>(
-    path: impl AsRef<Path>,
-    chunk: BTreeMap<Hash<K>, T>,
-) -> Result<(), Failure<TableAction>>
-where
-    T: Serialize,
-{
-    let path = path.as_ref();
-    debug!(path = %path.display(), "Writing chunk");
-    let yaml = serde_yaml::to_string(&chunk)
-        .map_err(Failure::wrap_with_path(TableAction::Serialize, path))?;
-    write(path, yaml)
-        .await
-        .map_err(Failure::wrap_with_path(TableAction::WriteChunk, path))?;
-    Ok(())
-}
-
-/// Update the items in a chunk
-///
-/// If `replace` is true then existing items are replaced
-async fn update_chunk<const K: usize, const C: usize, T>(
-    chunk_path: impl AsRef<Path>,
-    new_chunk: BTreeMap<Hash<K>, T>,
-    replace: bool,
-) -> Result<usize, Failure<TableAction>>
-where
-    T: DeserializeOwned + Serialize,
-{
-    let chunk_path = chunk_path.as_ref();
-    let mut added = 0;
-    let _lock = acquire_lock(chunk_path)
-        .await
-        .map_err(Failure::wrap(TableAction::UpdateChunk))?;
-    let mut chunk = if chunk_path.exists() {
-        read_chunk::<K, C, T>(chunk_path)
-            .await
-            .map_err(Failure::wrap(TableAction::UpdateChunk))?
-    } else {
-        BTreeMap::new()
-    };
-    for (hash, item) in new_chunk {
-        if replace || !chunk.contains_key(&hash) {
-            chunk.insert(hash, item);
-            added += 1;
-        }
-    }
-    write_chunk::<K, C, T>(chunk_path, chunk)
-        .await
-        .map_err(Failure::wrap(TableAction::UpdateChunk))?;
-    Ok(added)
-}
-
 /// Action being performed when a [`Failure<TableAction>`] occurred.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, ThisError)]
 pub enum TableAction {
@@ -315,6 +792,8 @@ pub enum TableAction {
     ReadChunk,
     #[error("write chunk")]
     WriteChunk,
+    #[error("remove chunk")]
+    RemoveChunk,
     #[error("read directory")]
     ReadDir,
     #[error("read entry")]
@@ -325,8 +804,78 @@ pub enum TableAction {
     Serialize,
     #[error("deserialize chunk")]
     Deserialize,
+    #[error("compress chunk")]
+    Compress,
+    #[error("decompress chunk")]
+    Decompress,
     #[error("update multiple chunks")]
     JoinTask,
+    #[error("get items")]
+    GetMany,
     #[error("set items")]
     SetMany,
+    #[error("remove items")]
+    RemoveMany,
+    #[error("reshard table")]
+    Reshard,
+    #[error("open table")]
+    Open,
+    #[error("read manifest")]
+    ReadManifest,
+    #[error("write manifest")]
+    WriteManifest,
+    #[error("migrate item")]
+    Migrate,
+    #[error("migrate all chunks")]
+    MigrateAll,
+    #[error("add index")]
+    AddIndex,
+    #[error("read index")]
+    ReadIndex,
+    #[error("write index")]
+    WriteIndex,
+    #[error("update index")]
+    UpdateIndex,
+    #[error("rebuild index")]
+    RebuildIndex,
+    #[error("stream items")]
+    Stream,
+    #[error("scan prefix")]
+    ScanPrefix,
+    #[error("scan range")]
+    Range,
+    #[error("find by abbreviated hash")]
+    FindByAbbrev,
+    #[error("commit transaction")]
+    Commit,
+    #[error("recover transaction")]
+    Recover,
+    #[error("append to write-ahead log")]
+    AppendLog,
+    #[error("read write-ahead log")]
+    ReadLog,
+    #[error("checkpoint write-ahead log")]
+    Checkpoint,
+    #[error("compare and set item")]
+    CompareAndSet,
+    #[error("update item")]
+    Update,
+    #[error("update items")]
+    UpdateMany,
+    #[error("clear stale locks")]
+    ClearStaleLocks,
+    #[error("acquire exclusive table lock")]
+    Exclusive,
+    #[error("check item exists")]
+    Contains,
+    #[error("count items")]
+    Len,
+    #[error("get all items leniently")]
+    GetAllLenient,
+    #[error("quarantine chunk")]
+    Quarantine,
+    #[error("verify table")]
+    Verify,
+    #[error("repair table")]
+    Repair,
 }
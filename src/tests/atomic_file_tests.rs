@@ -0,0 +1,74 @@
+use crate::atomic_file::{get_temp_path, write_atomic};
+use crate::tests::example_item::example_items;
+use crate::tests::test_directory::TestDirectory;
+use crate::{Table, TableAction};
+use rogue_logging::Failure;
+use std::fs::{read_dir, read_to_string};
+use std::io;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn write_atomic_creates_file() -> io::Result<()> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let path = test_dir.path.join("chunk.yml");
+
+    // Act
+    write_atomic(&path, "hello").await?;
+
+    // Assert
+    assert_eq!(read_to_string(&path)?, "hello");
+    assert!(!get_temp_path(&path).exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn write_atomic_replaces_existing_file() -> io::Result<()> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let path = test_dir.path.join("chunk.yml");
+    write_atomic(&path, "original contents").await?;
+
+    // Act
+    write_atomic(&path, "new").await?;
+
+    // Assert
+    assert_eq!(read_to_string(&path)?, "new");
+    assert!(!get_temp_path(&path).exists());
+    Ok(())
+}
+
+#[test]
+fn get_temp_path_is_sibling() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let path = test_dir.path.join("ab.yml");
+
+    // Act
+    let temp_path = get_temp_path(&path);
+
+    // Assert
+    assert_eq!(temp_path, test_dir.path.join("ab.yml.tmp"));
+}
+
+#[traced_test]
+#[tokio::test]
+async fn table_set_many_leaves_no_temp_files() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, _>::new(test_dir.path.clone());
+
+    // Act
+    table.set_many(example_items(), true).await?;
+
+    // Assert
+    let temp_files: Vec<_> = read_dir(&test_dir.path)
+        .expect("should read dir")
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "tmp"))
+        .collect();
+    assert!(temp_files.is_empty());
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::table::get_chunk_hash;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::rewrite_keeping_length_and_modified_time;
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::collections::BTreeMap;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn cache_hits_same_chunk() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table, items) = create_table(4).await?;
+
+    // Act
+    for hash in items.keys().filter(|hash| hash.as_bytes()[0] == 0x19) {
+        table.get(*hash).await?;
+    }
+
+    // Assert
+    let stats = table.cache_stats().expect("cache should be enabled");
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.chunks, 1);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn cache_invalidated_by_write() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table, items) = create_table(4).await?;
+    let (hash, item) = items.into_iter().next().expect("should have an item");
+    table.get(hash).await?;
+    let replacement = ExampleItem {
+        optional: Some("Replaced".to_owned()),
+        ..item
+    };
+
+    // Act
+    table.set(hash, replacement.clone()).await?;
+    let same_process = table.get(hash).await?;
+    let other = ExampleItem {
+        optional: Some("Replaced by another process".to_owned()),
+        ..replacement
+    };
+    Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .set(hash, other.clone())
+        .await?;
+    let other_process = table.get(hash).await?;
+
+    // Assert
+    assert_eq!(same_process, Some(replacement));
+    assert_eq!(other_process, Some(other));
+    let stats = table.cache_stats().expect("cache should be enabled");
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.hits, 0);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn cache_evicts_least_recently_used() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table, items) = create_table(1).await?;
+    let first = *items.keys().next().expect("should have an item");
+    let last = *items.keys().next_back().expect("should have an item");
+
+    // Act
+    table.get(first).await?;
+    table.get(last).await?;
+    table.get(first).await?;
+
+    // Assert
+    let stats = table.cache_stats().expect("cache should be enabled");
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.chunks, 1);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn cache_invalidated_by_rewrite_keeping_length_and_modified_time()
+-> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table, items) = create_table(4).await?;
+    let hash = *items.keys().next().expect("should have an item");
+    let renamed = Hash::<20>::new([0x00; 20]);
+    table.get(hash).await?;
+    let path = table.get_chunk_path(get_chunk_hash(hash));
+    rewrite_keeping_length_and_modified_time(&path, |yaml| {
+        yaml.replace(&hash.to_hex(), &renamed.to_hex())
+    });
+
+    // Act
+    let found = table.get(hash).await?;
+
+    // Assert
+    assert_eq!(found, None);
+    let stats = table.cache_stats().expect("cache should be enabled");
+    assert_eq!(stats.misses, 2);
+    Ok(())
+}
+
+async fn create_table(
+    capacity: usize,
+) -> Result<
+    (
+        TestDirectory,
+        Table<20, 1, ExampleItem>,
+        BTreeMap<Hash<20>, ExampleItem>,
+    ),
+    Failure<TableAction>,
+> {
+    let test_dir = TestDirectory::new();
+    let table = Table::new(test_dir.path.clone()).with_cache(capacity);
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    Ok((test_dir, table, items))
+}
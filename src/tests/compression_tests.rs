@@ -0,0 +1,101 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::path::Path;
+
+#[test]
+fn compression_from_path_uncompressed() {
+    // Arrange
+    let path = Path::new("ab.yml");
+
+    // Act
+    let compression = Compression::from_path(path);
+
+    // Assert
+    assert_eq!(compression, Compression::None);
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn zstd_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Compression::Zstd, "ac.yml.zst").await
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn gzip_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Compression::Gzip, "ac.yml.gz").await
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compression_reads_mixed_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = example_items();
+    let (first, second): (Vec<_>, Vec<_>) = items
+        .clone()
+        .into_iter()
+        .partition(|(hash, _)| hash.as_bytes()[0] == 0x19);
+    let uncompressed = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    uncompressed
+        .set_many(first.into_iter().collect(), true)
+        .await?;
+    let compressed = uncompressed.clone().with_compression(Compression::Zstd);
+    compressed
+        .set_many(second.into_iter().collect(), true)
+        .await?;
+
+    // Act
+    let output = compressed.get_all().await?;
+
+    // Assert
+    assert_eq!(output, items);
+    assert!(test_dir.path.join("19.yml").is_file());
+    assert!(test_dir.path.join("89.yml.zst").is_file());
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn compression_converts_chunk_on_write() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = example_items();
+    let uncompressed = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    uncompressed.set_many(items.clone(), true).await?;
+    let compressed = uncompressed.clone().with_compression(Compression::Zstd);
+    let (hash, item) = items.into_iter().next().expect("should have at least one");
+
+    // Act
+    compressed.remove(hash).await?;
+    compressed.set(hash, item.clone()).await?;
+
+    // Assert
+    assert!(!test_dir.path.join("19.yml").exists());
+    assert!(test_dir.path.join("19.yml.zst").is_file());
+    assert_eq!(uncompressed.get(hash).await?, Some(item));
+    Ok(())
+}
+
+async fn assert_round_trip(
+    compression: Compression,
+    expected_file: &str,
+) -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table =
+        Table::<20, 1, ExampleItem>::new(test_dir.path.clone()).with_compression(compression);
+    let items = example_items();
+
+    // Act
+    table.set_many(items.clone(), true).await?;
+    let output = table.get_all().await?;
+
+    // Assert
+    assert_eq!(output, items);
+    assert!(test_dir.path.join(expected_file).is_file());
+    assert!(!test_dir.path.join("ac.yml").exists());
+    Ok(())
+}
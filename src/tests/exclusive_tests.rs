@@ -0,0 +1,179 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::error::Error;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_blocks_other_writers_until_dropped() -> Result<(), Failure<TableAction>> {
+    assert_blocks_other_writers(LockOptions::default()).await
+}
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_blocks_other_writers_with_advisory_locks() -> Result<(), Failure<TableAction>> {
+    assert_blocks_other_writers(LockOptions {
+        advisory: true,
+        ..LockOptions::default()
+    })
+    .await
+}
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_waits_for_in_flight_writers() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table(LockOptions {
+        timeout: Duration::from_millis(200),
+        ..LockOptions::default()
+    });
+    let writer = table.lock_writer().await?;
+
+    // Act
+    let error = table.exclusive().await.err().expect("should time out");
+    drop(writer);
+    let exclusive = table.exclusive().await;
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::Exclusive);
+    assert!(exclusive.is_ok());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_breaks_writer_lock_of_exited_process() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(LockOptions::default());
+    let writer = test_dir.path.join("writer-4294967295-0.lock");
+    let yaml = "pid: 4294967295\nhost: other\nacquired: 0\n";
+    write(&writer, yaml).expect("should write lock");
+
+    // Act
+    let exclusive = table.exclusive().await?;
+
+    // Assert
+    assert!(!writer.exists());
+    drop(exclusive);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_lock_of_live_holder_is_not_broken_as_stale() -> Result<(), Failure<TableAction>>
+{
+    // Arrange
+    let options = LockOptions {
+        timeout: Duration::from_millis(200),
+        stale_after: Some(Duration::ZERO),
+        ..LockOptions::default()
+    };
+    let (test_dir, table) = create_table(options);
+    let other = table.clone();
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    let exclusive = table.exclusive().await?;
+
+    // Act
+    let blocked = other.set(hash, item).await;
+
+    // Assert
+    assert!(blocked.is_err());
+    assert!(test_dir.path.join("table.lock").exists());
+    assert!(!logs_contain("Breaking stale lock"));
+    drop(exclusive);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn writer_lock_of_live_holder_is_not_broken_as_stale() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table(LockOptions {
+        timeout: Duration::from_millis(200),
+        stale_after: Some(Duration::ZERO),
+        ..LockOptions::default()
+    });
+    let writer = table.lock_writer().await?;
+
+    // Act
+    let exclusive = table.exclusive().await;
+
+    // Assert
+    assert!(exclusive.is_err());
+    assert!(!logs_contain("Breaking stale lock"));
+    drop(writer);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn exclusive_lock_is_refreshed_while_held() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(LockOptions {
+        stale_after: Some(Duration::from_secs(4)),
+        ..LockOptions::default()
+    });
+    let lock = test_dir.path.join("table.lock");
+    let exclusive = table.exclusive().await?;
+    let acquired = read_lock_info(&lock);
+
+    // Act
+    sleep(Duration::from_millis(1500)).await;
+    let refreshed = read_lock_info(&lock);
+
+    // Assert
+    assert_eq!(refreshed.pid, acquired.pid);
+    assert!(refreshed.acquired > acquired.acquired);
+    drop(exclusive);
+    assert!(!lock.exists());
+    Ok(())
+}
+
+async fn assert_blocks_other_writers(options: LockOptions) -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(options);
+    let other = table.clone().with_lock_options(LockOptions {
+        fail_fast: true,
+        ..options
+    });
+    let mut items = example_items().into_iter();
+    let (first_hash, first) = items.next().expect("should have an item");
+    let (second_hash, second) = items.next().expect("should have an item");
+
+    // Act
+    let exclusive = table.exclusive().await?;
+    let blocked = other.set(first_hash, first.clone()).await;
+    exclusive.set(first_hash, first.clone()).await?;
+    drop(exclusive);
+    other.set(second_hash, second.clone()).await?;
+
+    // Assert
+    let lock_error = blocked
+        .as_ref()
+        .err()
+        .and_then(|error| error.source())
+        .and_then(|source| source.source())
+        .and_then(|source| source.downcast_ref::<LockError>());
+    assert!(matches!(lock_error, Some(LockError::Busy { .. })));
+    assert_eq!(table.get(first_hash).await?, Some(first));
+    assert_eq!(table.get(second_hash).await?, Some(second));
+    assert!(!test_dir.path.join("table.lock").exists());
+    Ok(())
+}
+
+fn create_table(options: LockOptions) -> (TestDirectory, Table<20, 1, ExampleItem>) {
+    let test_dir = TestDirectory::new();
+    let table = Table::new(test_dir.path.clone()).with_lock_options(options);
+    (test_dir, table)
+}
+
+fn read_lock_info(path: &Path) -> LockInfo {
+    let yaml = read_to_string(path).expect("should read lock");
+    serde_yaml::from_str(&yaml).expect("should deserialize lock")
+}
@@ -2,7 +2,8 @@ use crate::tests::example_item::{ExampleItem, example_items};
 use crate::tests::helpers::{PKG_NAME, get_temp_dir};
 use crate::tests::snapshots::DirectorySnapshot;
 use crate::tests::test_directory::TestDirectory;
-use crate::{FileTable, FileTableAction, Hash};
+use crate::{FileTable, FileTableAction, Hash, LockOptions};
+use futures::TryStreamExt;
 use rogue_logging::Failure;
 use std::collections::BTreeMap;
 use std::fs::{create_dir_all, write};
@@ -97,6 +98,126 @@ async fn file_table_get_missing_file() -> Result<(), Failure<FileTableAction>> {
     Ok(())
 }
 
+#[traced_test]
+#[tokio::test]
+async fn file_table_contains_and_len() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let examples = create_example_files();
+    let expected_count = examples.len();
+    let existing = *examples.keys().next().expect("should have an example");
+    let missing = Hash::<20>::new([0xab; 20]);
+    let (_test_dir, table) = create_file_table();
+    table.set_many(examples).await?;
+
+    // Act
+    let len = table.len().await?;
+
+    // Assert
+    assert_eq!(len, expected_count);
+    assert!(table.contains(existing));
+    assert!(!table.contains(missing));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_stream() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let examples = create_example_files();
+    let (_test_dir, table) = create_file_table();
+    table.set_many(examples.clone()).await?;
+
+    // Act
+    let hashes: Vec<Hash<20>> = table
+        .with_read_ahead(1)
+        .stream()
+        .map_ok(|(hash, _)| hash)
+        .try_collect()
+        .await?;
+
+    // Assert
+    assert_eq!(hashes, examples.into_keys().collect::<Vec<_>>());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_scan_prefix_and_find_by_abbrev() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let examples = create_example_files();
+    let (_test_dir, table) = create_file_table();
+    table.set_many(examples).await?;
+
+    // Act
+    let paths = table.scan_prefix(&[0xac]).await?;
+    let found = table.find_by_abbrev("acc2").await?;
+
+    // Assert
+    assert_eq!(paths.len(), 3);
+    let (hash, path) = found.expect("should find file");
+    assert_eq!(&hash.as_bytes()[..2], &[0xac, 0xc2]);
+    assert_eq!(paths.get(&hash), Some(&path));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_set_fails_fast_when_locked() {
+    // Arrange
+    let (hash, source) = create_example_file(
+        example_items()
+            .into_values()
+            .next()
+            .expect("should have an example"),
+    );
+    let (_test_dir, table) = create_file_table();
+    let table = table.with_lock_options(LockOptions {
+        fail_fast: true,
+        ..LockOptions::default()
+    });
+    let stored_path = table.get_path(hash);
+    let lock_path = stored_path.with_extension("lock");
+    create_dir_all(lock_path.parent().expect("should have parent")).expect("should create dir");
+    write(&lock_path, "").expect("should write lock");
+
+    // Act
+    let error = table.set(hash, source).await.expect_err("should fail");
+
+    // Assert
+    assert_eq!(error.action(), &FileTableAction::Set);
+    assert!(!stored_path.exists());
+    assert!(lock_path.exists());
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_set_many_reports_failures_with_bounded_concurrency() {
+    // Arrange
+    let examples = create_example_files();
+    let expected_count = examples.len();
+    let locked_hash = *examples.keys().next().expect("should have an example");
+    let (_test_dir, table) = create_file_table();
+    let table = table.with_concurrency(2).with_lock_options(LockOptions {
+        fail_fast: true,
+        ..LockOptions::default()
+    });
+    let lock_path = table.get_path(locked_hash).with_extension("lock");
+    create_dir_all(lock_path.parent().expect("should have parent")).expect("should create dir");
+    write(&lock_path, "").expect("should write lock");
+
+    // Act
+    let error = table.set_many(examples).await.expect_err("should fail");
+
+    // Assert
+    assert_eq!(error.action(), &FileTableAction::SetMany);
+    assert_eq!(error.get("failed"), Some("1".to_owned()));
+    assert_eq!(
+        error.get("succeeded"),
+        Some((expected_count - 1).to_string())
+    );
+    assert!(table.get(locked_hash).is_none());
+}
+
 #[traced_test]
 #[test]
 fn file_table_empty_get_all() {
@@ -115,10 +236,7 @@ fn file_table_empty_get_all() {
 
 fn create_file_table() -> (TestDirectory, FileTable<20, 1>) {
     let test_dir = TestDirectory::new();
-    let table = FileTable::<20, 1> {
-        directory: test_dir.path.clone(),
-        extension: "txt".to_owned(),
-    };
+    let table = FileTable::<20, 1>::new(test_dir.path.clone(), "txt");
     (test_dir, table)
 }
 
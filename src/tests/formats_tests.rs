@@ -0,0 +1,102 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::fs::read_dir;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn yaml_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Yaml).await
+}
+
+#[cfg(feature = "json")]
+#[traced_test]
+#[tokio::test]
+async fn json_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Json).await
+}
+
+#[cfg(feature = "json-lines")]
+#[traced_test]
+#[tokio::test]
+async fn json_lines_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(JsonLines).await
+}
+
+#[cfg(feature = "toml")]
+#[traced_test]
+#[tokio::test]
+async fn toml_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Toml).await
+}
+
+#[cfg(feature = "msgpack")]
+#[traced_test]
+#[tokio::test]
+async fn msgpack_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(MessagePack).await
+}
+
+#[cfg(feature = "cbor")]
+#[traced_test]
+#[tokio::test]
+async fn cbor_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Cbor).await
+}
+
+#[cfg(feature = "postcard")]
+#[traced_test]
+#[tokio::test]
+async fn postcard_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
+    assert_round_trip(Postcard).await
+}
+
+#[cfg(feature = "json-lines")]
+#[test]
+fn json_lines_writes_one_item_per_line() -> Result<(), FormatError> {
+    // Arrange
+    let items = example_items();
+
+    // Act
+    let bytes = JsonLines.serialize(&items)?;
+
+    // Assert
+    let text = String::from_utf8(bytes).expect("should be utf-8");
+    assert_eq!(text.lines().count(), items.len());
+    let output = JsonLines.deserialize::<Hash<20>, ExampleItem>(text.as_bytes())?;
+    assert_eq!(output, items);
+    Ok(())
+}
+
+async fn assert_round_trip<F: ChunkFormat>(format: F) -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone()).with_format(format.clone());
+    let items = example_items();
+
+    // Act
+    table.set_many(items.clone(), true).await?;
+    let output = table.get_all().await?;
+
+    // Assert
+    assert_eq!(output, items);
+    let extensions: Vec<_> = read_dir(&test_dir.path)
+        .expect("should read dir")
+        .flatten()
+        .map(|entry| {
+            entry
+                .path()
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_string())
+        })
+        .collect();
+    assert!(!extensions.is_empty());
+    assert!(
+        extensions
+            .iter()
+            .all(|ext| ext.as_deref() == Some(format.extension()))
+    );
+    Ok(())
+}
@@ -86,3 +86,31 @@ fn hash_from_string_invalid_chars() {
     // Assert
     assert!(result.is_err());
 }
+
+#[test]
+fn hash_serialize_human_readable() {
+    // Arrange
+    let hash = Hash::new(VALID_BYTES);
+
+    // Act
+    let yaml = serde_yaml::to_string(&hash).expect("should serialize");
+
+    // Assert
+    assert_eq!(yaml.trim(), VALID_HEX);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn hash_serialize_binary() {
+    // Arrange
+    let hash = Hash::new(VALID_BYTES);
+
+    // Act
+    let bytes = rmp_serde::to_vec(&hash).expect("should serialize");
+    let output: Hash<20> = rmp_serde::from_slice(&bytes).expect("should deserialize");
+
+    // Assert
+    assert_eq!(bytes.len(), VALID_BYTES.len() + 2);
+    assert_eq!(bytes.get(2..), Some(VALID_BYTES.as_slice()));
+    assert_eq!(output, hash);
+}
@@ -1,5 +1,9 @@
+use crate::tests::example_item::ExampleItem;
+use crate::tests::test_directory::TestDirectory;
+use crate::{Hash, Table};
 use std::env::temp_dir;
-use std::path::PathBuf;
+use std::fs::{File, metadata, read_to_string, write};
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 pub(crate) const PKG_NAME: &str = "flat_db";
@@ -12,3 +16,39 @@ pub(crate) fn get_temp_dir(sub_dir_name: &str) -> PathBuf {
         .to_string();
     temp_dir().join(sub_dir_name).join(timestamp)
 }
+
+/// Rewrite a chunk keeping its length and modification time.
+pub(crate) fn rewrite_keeping_length_and_modified_time(
+    path: &Path,
+    rewrite: impl FnOnce(String) -> String,
+) {
+    let metadata = metadata(path).expect("should read metadata");
+    let modified = metadata.modified().expect("should have modification time");
+    let yaml = rewrite(read_to_string(path).expect("should read chunk"));
+    assert_eq!(
+        u64::try_from(yaml.len()).expect("should fit in u64"),
+        metadata.len()
+    );
+    write(path, yaml).expect("should write chunk");
+    File::options()
+        .write(true)
+        .open(path)
+        .expect("should open chunk")
+        .set_modified(modified)
+        .expect("should set modification time");
+}
+
+/// Create a hash with the first two bytes set.
+pub(crate) fn create_hash(first: u8, second: u8) -> Hash<20> {
+    let mut bytes = [0; 20];
+    bytes[0] = first;
+    bytes[1] = second;
+    Hash::new(bytes)
+}
+
+/// Create a table with default options in a new test directory.
+pub(crate) fn create_table() -> (TestDirectory, Table<20, 1, ExampleItem>) {
+    let test_dir = TestDirectory::new();
+    let table = Table::new(test_dir.path.clone());
+    (test_dir, table)
+}
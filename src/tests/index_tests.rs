@@ -0,0 +1,172 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::collections::BTreeSet;
+use tracing_test::traced_test;
+
+const INDEX: &str = "by_success";
+
+#[traced_test]
+#[tokio::test]
+async fn index_set_many() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = create_table(&test_dir);
+    let items = example_items();
+    let expected: BTreeSet<Hash<20>> = items
+        .iter()
+        .filter(|(_, item)| item.success)
+        .map(|(hash, _)| *hash)
+        .collect();
+
+    // Act
+    table.set_many(items, false).await?;
+
+    // Assert
+    let output = table.index(INDEX).get("true").await?;
+    assert_eq!(output, expected);
+    let items = table.index(INDEX).get_items("true").await?;
+    assert_eq!(items.keys().copied().collect::<BTreeSet<_>>(), expected);
+    assert!(items.values().all(|item| item.success));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_set_replaces_key() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = create_table(&test_dir);
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    let (hash, item) = items
+        .into_iter()
+        .find(|(_, item)| !item.success)
+        .expect("should have a failed item");
+
+    // Act
+    table
+        .set(
+            hash,
+            ExampleItem {
+                success: true,
+                ..item
+            },
+        )
+        .await?;
+
+    // Assert
+    assert!(table.index(INDEX).get("true").await?.contains(&hash));
+    assert!(!table.index(INDEX).get("false").await?.contains(&hash));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_remove() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = create_table(&test_dir);
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    let hash = *items.keys().next().expect("should have an item");
+
+    // Act
+    table.remove(hash).await?;
+
+    // Assert
+    assert!(!table.index(INDEX).get("true").await?.contains(&hash));
+    assert!(!table.index(INDEX).get("false").await?.contains(&hash));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_rebuild() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = example_items();
+    Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .set_many(items.clone(), false)
+        .await?;
+    let table = create_table(&test_dir);
+    let before = table.index(INDEX).get("false").await?;
+
+    // Act
+    let count = table.rebuild_index(INDEX).await?;
+
+    // Assert
+    assert!(before.is_empty());
+    assert_eq!(count, items.len());
+    let output = table.index(INDEX).get("false").await?;
+    assert_eq!(
+        output.len(),
+        items.values().filter(|item| !item.success).count()
+    );
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_rebuild_unknown() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = create_table(&test_dir);
+
+    // Act
+    let error = table
+        .rebuild_index("missing")
+        .await
+        .expect_err("should fail");
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::RebuildIndex);
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_get_items_unknown() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = create_table(&test_dir);
+
+    // Act
+    let error = table
+        .index("missing")
+        .get_items("true")
+        .await
+        .expect_err("should fail");
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::ReadIndex);
+}
+
+#[traced_test]
+#[tokio::test]
+async fn index_rejects_name_outside_index_directory() {
+    for name in ["", ".", "..", "../x", "a/b", "a\\b"] {
+        // Arrange
+        let test_dir = TestDirectory::new();
+
+        // Act
+        let result = Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+            .with_index(name, |item: &ExampleItem| item.success.to_string());
+        let read = Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+            .index(name)
+            .get("true")
+            .await;
+
+        // Assert
+        let error = result.err().expect("should fail");
+        assert_eq!(error.action(), &TableAction::AddIndex);
+        assert!(read.is_err());
+        assert!(!test_dir.path.join("x").exists());
+    }
+}
+
+fn create_table(test_dir: &TestDirectory) -> Table<20, 1, ExampleItem> {
+    Table::new(test_dir.path.clone())
+        .with_index(INDEX, |item: &ExampleItem| item.success.to_string())
+        .expect("index name should be valid")
+}
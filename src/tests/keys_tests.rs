@@ -0,0 +1,124 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
+use crate::*;
+use futures::TryStreamExt;
+use rogue_logging::Failure;
+use std::fs::create_dir_all;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn contains_existing_and_missing_items() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    let missing = Hash::<20>::new([0xab; 20]);
+
+    // Act
+    let found_existing = table.contains(existing).await?;
+    let found_missing = table.contains(missing).await?;
+
+    // Assert
+    assert!(found_existing);
+    assert!(!found_missing);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn len_counts_items() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    create_dir_all(&test_dir.path).expect("should create dir");
+    let items = example_items();
+
+    // Act
+    let empty = table.is_empty().await?;
+    table.set_many(items.clone(), true).await?;
+    let len = table.len().await?;
+
+    // Assert
+    assert!(empty);
+    assert_eq!(len, items.len());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn keys_skip_item_bodies() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    let other_type = Table::<20, 1, u32>::new(test_dir.path.clone());
+
+    // Act
+    let found = other_type.contains(existing).await?;
+    let len = other_type.len().await?;
+    let get_result = other_type.get(existing).await;
+
+    // Assert
+    assert!(found);
+    assert_eq!(len, items.len());
+    assert!(get_result.is_err());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn keys_include_wal_entries() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_wal(DEFAULT_CHECKPOINT_BYTES);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let removed = *items.keys().next().expect("should have an item");
+    let added = Hash::<20>::new([0xab; 20]);
+    table.remove(removed).await?;
+    table
+        .set(
+            added,
+            ExampleItem {
+                hash: added,
+                success: true,
+                optional: None,
+            },
+        )
+        .await?;
+
+    // Act
+    let keys: Vec<Hash<20>> = table.keys().try_collect().await?;
+
+    // Assert
+    assert_eq!(keys.len(), items.len());
+    assert!(!keys.contains(&removed));
+    assert!(keys.contains(&added));
+    assert!(!table.contains(removed).await?);
+    assert!(table.contains(added).await?);
+    Ok(())
+}
+
+#[cfg(feature = "postcard")]
+#[traced_test]
+#[tokio::test]
+async fn keys_deserialize_items_of_non_self_describing_formats() -> Result<(), Failure<TableAction>>
+{
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_format(Postcard);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+
+    // Act
+    let found = table.contains(existing).await?;
+    let len = table.len().await?;
+
+    // Assert
+    assert!(found);
+    assert_eq!(len, items.len());
+    Ok(())
+}
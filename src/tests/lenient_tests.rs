@@ -0,0 +1,155 @@
+use crate::table::get_chunk_hash;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_dir, read_to_string, write};
+use std::path::PathBuf;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn lenient_read_skips_bad_items() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let (good, bad, path) = write_chunk_with_bad_items(&table);
+
+    // Act
+    let strict = table.get_all().await;
+    let read = table.get_all_lenient().await?;
+
+    // Assert
+    assert!(strict.is_err());
+    assert_eq!(read.items.keys().copied().collect::<Vec<_>>(), vec![good]);
+    assert_eq!(read.diagnostics.len(), 2);
+    let bad_item = read
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.key == Some(bad.to_hex()))
+        .expect("should report bad item");
+    assert_eq!(bad_item.path, path);
+    assert!(bad_item.message.contains("expected a boolean"));
+    assert!(bad_item.message.contains("line 7"));
+    assert!(bad_item.quarantined.is_none());
+    assert!(
+        read.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.key.as_deref() == Some("not-a-hash"))
+    );
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn lenient_read_skips_unreadable_chunk() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let broken = *items.keys().next().expect("should have an item");
+    let path = table.get_chunk_path(get_chunk_hash(broken));
+    write(&path, "key: [unclosed").expect("should write chunk");
+    let expected: BTreeMap<Hash<20>, ExampleItem> = items
+        .into_iter()
+        .filter(|(hash, _)| get_chunk_hash::<20, 1>(*hash) != get_chunk_hash(broken))
+        .collect();
+
+    // Act
+    let read = table.get_all_lenient().await?;
+
+    // Assert
+    assert_eq!(read.items, expected);
+    assert_eq!(read.diagnostics.len(), 1);
+    let diagnostic = read.diagnostics.first().expect("should have a diagnostic");
+    assert_eq!(diagnostic.path, path);
+    assert_eq!(diagnostic.key, None);
+    assert!(diagnostic.message.contains("line"));
+    assert!(path.is_file());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn lenient_read_quarantines_bad_items() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let table = table.with_quarantine(true);
+    let (good, bad, _) = write_chunk_with_bad_items(&table);
+
+    // Act
+    let read = table.get_all_lenient().await?;
+    let strict = table.get_all().await?;
+
+    // Assert
+    assert_eq!(read.diagnostics.len(), 2);
+    assert_eq!(strict.keys().copied().collect::<Vec<_>>(), vec![good]);
+    let quarantined = list_quarantine(&test_dir);
+    assert_eq!(quarantined.len(), 1);
+    let path = quarantined.first().expect("should have a quarantined file");
+    for diagnostic in &read.diagnostics {
+        assert_eq!(diagnostic.quarantined.as_ref(), Some(path));
+    }
+    let contents = read_to_string(path).expect("should read quarantined items");
+    assert!(contents.contains(&bad.to_hex()));
+    assert!(contents.contains("not-a-hash"));
+    assert!(!contents.contains(&good.to_hex()));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn lenient_read_quarantines_unreadable_chunk() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let table = table.with_quarantine(true);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let broken = *items.keys().next().expect("should have an item");
+    let path = table.get_chunk_path(get_chunk_hash(broken));
+    write(&path, "key: [unclosed").expect("should write chunk");
+
+    // Act
+    let read = table.get_all_lenient().await?;
+    let strict = table.get_all().await?;
+
+    // Assert
+    assert_eq!(read.items, strict);
+    assert!(!path.exists());
+    let quarantined = list_quarantine(&test_dir);
+    assert_eq!(quarantined.len(), 1);
+    let diagnostic = read.diagnostics.first().expect("should have a diagnostic");
+    assert_eq!(diagnostic.quarantined.as_ref(), quarantined.first());
+    let contents = read_to_string(quarantined.first().expect("should be quarantined"))
+        .expect("should read quarantined chunk");
+    assert_eq!(contents, "key: [unclosed");
+    Ok(())
+}
+
+/// Write a chunk with one good item, one item with an invalid field and an invalid
+/// key.
+///
+/// Returns the good and bad hashes and the chunk path.
+fn write_chunk_with_bad_items(table: &Table<20, 1, ExampleItem>) -> (Hash<20>, Hash<20>, PathBuf) {
+    let good = Hash::<20>::new([0xab; 20]);
+    let mut bad_bytes = [0xab; 20];
+    bad_bytes[19] = 0xcd;
+    let bad = Hash::<20>::new(bad_bytes);
+    let path = table.get_chunk_path(get_chunk_hash(good));
+    let yaml = format!(
+        "{good}:\n  hash: {good}\n  success: true\n  optional: null\n\
+         {bad}:\n  hash: {bad}\n  success: maybe\n  optional: null\n\
+         not-a-hash:\n  hash: {bad}\n  success: true\n  optional: null\n"
+    );
+    create_dir_all(&table.directory).expect("should create dir");
+    write(&path, yaml).expect("should write chunk");
+    (good, bad, path)
+}
+
+fn list_quarantine(test_dir: &TestDirectory) -> Vec<PathBuf> {
+    read_dir(test_dir.path.join("quarantine"))
+        .expect("should read quarantine")
+        .map(|entry| entry.expect("should read entry").path())
+        .collect()
+}
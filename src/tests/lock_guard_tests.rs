@@ -1,8 +1,16 @@
-use crate::TableAction;
-use crate::lock_guard::acquire_lock;
+use crate::lock_guard::{LockInfo, acquire_lock, acquire_shared_lock};
 use crate::tests::test_directory::TestDirectory;
+use crate::{
+    DEFAULT_LOCK_RETRY_DELAY, Hash, LockBackoff, LockError, LockOptions, Table, TableAction,
+};
 use rogue_logging::Failure;
-use std::time::Duration;
+use std::env;
+use std::error::Error;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
 use tokio::time::sleep;
 use tracing_test::traced_test;
 
@@ -12,7 +20,7 @@ async fn acquire_lock_creates_lock_file() -> Result<(), Failure<TableAction>> {
     let test_dir = TestDirectory::new();
     let chunk_path = test_dir.path.join("chunk.yml");
     let expected_lock = test_dir.path.join("chunk.lock");
-    let guard = acquire_lock(&chunk_path).await?;
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
     assert!(expected_lock.exists());
     drop(guard);
     Ok(())
@@ -24,21 +32,45 @@ async fn drop_removes_lock_file() -> Result<(), Failure<TableAction>> {
     let test_dir = TestDirectory::new();
     let chunk_path = test_dir.path.join("chunk.yml");
     let expected_lock = test_dir.path.join("chunk.lock");
-    let guard = acquire_lock(&chunk_path).await?;
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
     assert!(expected_lock.exists());
     drop(guard);
     assert!(!expected_lock.exists());
     Ok(())
 }
 
+#[traced_test]
+#[tokio::test]
+async fn drop_keeps_lock_file_acquired_by_another_owner() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let lock = test_dir.path.join("chunk.lock");
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
+    let other = LockInfo {
+        host: "other".to_owned(),
+        ..LockInfo::current()
+    };
+    write_lock(&lock, &other);
+
+    // Act
+    drop(guard);
+
+    // Assert
+    let yaml = read_to_string(&lock).expect("should read lock");
+    let info: LockInfo = serde_yaml::from_str(&yaml).expect("should deserialize lock");
+    assert_eq!(info, other);
+    Ok(())
+}
+
 #[traced_test]
 #[tokio::test]
 async fn second_acquire_succeeds_after_drop() -> Result<(), Failure<TableAction>> {
     let test_dir = TestDirectory::new();
     let chunk_path = test_dir.path.join("chunk.yml");
-    let guard = acquire_lock(&chunk_path).await?;
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
     drop(guard);
-    let _guard2 = acquire_lock(&chunk_path).await?;
+    let _guard2 = acquire_lock(&chunk_path, &LockOptions::default()).await?;
     Ok(())
 }
 
@@ -47,8 +79,10 @@ async fn second_acquire_succeeds_after_drop() -> Result<(), Failure<TableAction>
 async fn acquire_times_out_when_locked() {
     let test_dir = TestDirectory::new();
     let chunk_path = test_dir.path.join("chunk.yml");
-    let _guard = acquire_lock(&chunk_path).await.expect("first lock");
-    let result = acquire_lock(&chunk_path).await;
+    let _guard = acquire_lock(&chunk_path, &LockOptions::default())
+        .await
+        .expect("first lock");
+    let result = acquire_lock(&chunk_path, &LockOptions::default()).await;
     assert!(result.is_err());
 }
 
@@ -57,13 +91,456 @@ async fn acquire_times_out_when_locked() {
 async fn acquire_succeeds_after_concurrent_release() -> Result<(), Failure<TableAction>> {
     let test_dir = TestDirectory::new();
     let chunk_path = test_dir.path.join("chunk.yml");
-    let guard = acquire_lock(&chunk_path).await?;
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
     let path = chunk_path.clone();
     let handle = tokio::spawn(async move {
         sleep(Duration::from_millis(100)).await;
         drop(guard);
     });
-    let _guard2 = acquire_lock(&path).await?;
+    let _guard2 = acquire_lock(&path, &LockOptions::default()).await?;
+    handle.await.expect("spawned task should complete");
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_lock_records_owner() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+
+    // Act
+    let _guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
+
+    // Assert
+    let yaml = read_to_string(test_dir.path.join("chunk.lock")).expect("should read lock");
+    let info: LockInfo = serde_yaml::from_str(&yaml).expect("should deserialize lock");
+    assert_eq!(info.pid, process::id());
+    assert_eq!(info.host, LockInfo::current().host);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_breaks_lock_of_exited_process() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let owner = LockInfo {
+        pid: u32::MAX,
+        ..LockInfo::current()
+    };
+    write_lock(&test_dir.path.join("chunk.lock"), &owner);
+
+    // Act
+    let result = acquire_lock(&chunk_path, &LockOptions::default()).await;
+
+    // Assert
+    if cfg!(target_os = "linux") {
+        result?;
+        assert!(logs_contain("owner process no longer exists"));
+    }
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_breaks_lock_older_than_stale_after() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let owner = LockInfo {
+        host: "other".to_owned(),
+        acquired: 0,
+        ..LockInfo::current()
+    };
+    write_lock(&test_dir.path.join("chunk.lock"), &owner);
+
+    // Act
+    let _guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
+
+    // Assert
+    assert!(logs_contain("Breaking stale lock"));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_keeps_old_lock_of_running_process() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let owner = LockInfo {
+        acquired: 0,
+        ..LockInfo::current()
+    };
+    write_lock(&test_dir.path.join("chunk.lock"), &owner);
+    let options = LockOptions {
+        timeout: Duration::from_millis(200),
+        stale_after: Some(Duration::ZERO),
+        ..LockOptions::default()
+    };
+
+    // Act
+    let result = acquire_lock(&chunk_path, &options).await;
+
+    // Assert
+    assert!(result.is_err());
+    assert!(!logs_contain("Breaking stale lock"));
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_keeps_old_lock_of_running_process_without_stale_after() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let owner = LockInfo {
+        acquired: 0,
+        ..LockInfo::current()
+    };
+    write_lock(&test_dir.path.join("chunk.lock"), &owner);
+    let options = LockOptions {
+        stale_after: None,
+        ..LockOptions::default()
+    };
+
+    // Act
+    let result = acquire_lock(&chunk_path, &options).await;
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[traced_test]
+#[tokio::test]
+async fn clear_stale_locks_removes_only_stale_locks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, String>::new(test_dir.path.clone());
+    let stale = LockInfo {
+        host: "other".to_owned(),
+        acquired: 0,
+        ..LockInfo::current()
+    };
+    let nested = test_dir.path.join("indexes").join("name");
+    create_dir_all(&nested).expect("should create dir");
+    write_lock(&test_dir.path.join("19.lock"), &stale);
+    write_lock(&nested.join("ab.lock"), &stale);
+    let _guard = acquire_lock(test_dir.path.join("89.yml"), &LockOptions::default()).await?;
+
+    // Act
+    let removed = table.clear_stale_locks().await?;
+
+    // Assert
+    assert_eq!(removed, 2);
+    assert!(!test_dir.path.join("19.lock").exists());
+    assert!(!nested.join("ab.lock").exists());
+    assert!(test_dir.path.join("89.lock").exists());
+    Ok(())
+}
+
+fn write_lock(path: &Path, info: &LockInfo) {
+    let yaml = serde_yaml::to_string(info).expect("should serialize lock");
+    write(path, yaml).expect("should write lock");
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_timeout_reports_wait_and_holder() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let _guard = acquire_lock(&chunk_path, &LockOptions::default())
+        .await
+        .expect("first lock");
+    let options = LockOptions {
+        timeout: Duration::from_millis(200),
+        ..LockOptions::default()
+    };
+
+    // Act
+    let error = acquire_lock(&chunk_path, &options)
+        .await
+        .err()
+        .expect("should time out");
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::AcquireLock);
+    let lock_error = error
+        .source()
+        .and_then(|source| source.downcast_ref::<LockError>())
+        .expect("should be a lock error");
+    assert!(matches!(
+        lock_error,
+        LockError::Timeout { waited, holder: Some(holder) }
+            if *waited >= options.timeout && holder.pid == process::id()
+    ));
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_fail_fast_returns_busy_without_waiting() {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let _guard = acquire_lock(&chunk_path, &LockOptions::default())
+        .await
+        .expect("first lock");
+    let options = LockOptions {
+        fail_fast: true,
+        ..LockOptions::default()
+    };
+    let start = Instant::now();
+
+    // Act
+    let error = acquire_lock(&chunk_path, &options)
+        .await
+        .err()
+        .expect("should fail");
+
+    // Assert
+    assert!(start.elapsed() < DEFAULT_LOCK_RETRY_DELAY);
+    let lock_error = error
+        .source()
+        .and_then(|source| source.downcast_ref::<LockError>());
+    assert!(matches!(
+        lock_error,
+        Some(LockError::Busy { holder: Some(_) })
+    ));
+}
+
+#[traced_test]
+#[tokio::test]
+async fn acquire_with_exponential_backoff_succeeds_after_release()
+-> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let guard = acquire_lock(&chunk_path, &LockOptions::default()).await?;
+    let options = LockOptions {
+        backoff: LockBackoff::Exponential {
+            initial: Duration::from_millis(10),
+            max: Duration::from_millis(100),
+        },
+        ..LockOptions::default()
+    };
+    let handle = tokio::spawn(async move {
+        sleep(Duration::from_millis(200)).await;
+        drop(guard);
+    });
+
+    // Act
+    let _guard2 = acquire_lock(&chunk_path, &options).await?;
+
+    // Assert
     handle.await.expect("spawned task should complete");
     Ok(())
 }
+
+#[traced_test]
+#[tokio::test]
+async fn advisory_lock_excludes_and_releases() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let lock_path = test_dir.path.join("chunk.lock");
+    let options = LockOptions {
+        advisory: true,
+        fail_fast: true,
+        ..LockOptions::default()
+    };
+
+    // Act
+    let guard = acquire_lock(&chunk_path, &options).await?;
+    let busy = acquire_lock(&chunk_path, &options).await;
+    drop(guard);
+
+    // Assert
+    assert!(busy.is_err());
+    assert!(!lock_path.exists());
+    let _guard = acquire_lock(&chunk_path, &options).await?;
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn advisory_lock_ignores_abandoned_lock_file() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    write_lock(&test_dir.path.join("chunk.lock"), &LockInfo::current());
+    let options = LockOptions {
+        advisory: true,
+        fail_fast: true,
+        ..LockOptions::default()
+    };
+
+    // Act
+    let _guard = acquire_lock(&chunk_path, &options).await?;
+
+    // Assert
+    assert!(test_dir.path.join("chunk.lock").exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn shared_lock_in_another_process_allows_readers_and_excludes_writers()
+-> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let options = LockOptions {
+        advisory: true,
+        fail_fast: true,
+        ..LockOptions::default()
+    };
+    let mut child = spawn_lock_holder(&chunk_path, true).await;
+
+    // Act
+    let reader = acquire_shared_lock(&chunk_path, &options).await;
+    let writer = acquire_lock(&chunk_path, &options).await;
+    drop(reader);
+
+    // Assert
+    let lock_error = writer
+        .as_ref()
+        .err()
+        .and_then(|error| error.source())
+        .and_then(|source| source.downcast_ref::<LockError>());
+    assert!(matches!(lock_error, Some(LockError::Busy { .. })));
+    let status = child.wait().await.expect("child should exit");
+    assert!(status.success());
+    let _writer = acquire_lock(&chunk_path, &options).await?;
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn writer_in_another_process_excludes_readers() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let chunk_path = test_dir.path.join("chunk.yml");
+    let options = LockOptions {
+        advisory: true,
+        fail_fast: true,
+        ..LockOptions::default()
+    };
+    let waiting = LockOptions {
+        fail_fast: false,
+        timeout: Duration::from_secs(10),
+        ..options
+    };
+    let mut child = spawn_lock_holder(&chunk_path, false).await;
+
+    // Act
+    let busy = acquire_shared_lock(&chunk_path, &options).await;
+    let reader = acquire_shared_lock(&chunk_path, &waiting).await?;
+
+    // Assert
+    assert!(busy.is_err());
+    let status = child.wait().await.expect("child should exit");
+    assert!(status.success());
+    drop(reader);
+    assert!(!test_dir.path.join("chunk.lock").exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn shared_reads_never_observe_partial_writes() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let options = LockOptions {
+        advisory: true,
+        lock_free_reads: false,
+        backoff: LockBackoff::Fixed(Duration::from_millis(1)),
+        timeout: Duration::from_secs(10),
+        ..LockOptions::default()
+    };
+    let table = Table::<20, 1, String>::new(test_dir.path.clone())
+        .with_atomic_writes(false)
+        .with_lock_options(options);
+    let hash = Hash::<20>::new([0x19; 20]);
+    table.set(hash, "a".repeat(100_000)).await?;
+    let writer = table.clone();
+    let handle = tokio::spawn(async move {
+        for character in ["b", "c", "d", "e", "f"] {
+            writer.set(hash, character.repeat(100_000)).await?;
+        }
+        Ok::<(), Failure<TableAction>>(())
+    });
+
+    // Act
+    let mut reads = Vec::new();
+    while !handle.is_finished() {
+        reads.push(table.get(hash).await?);
+        sleep(Duration::from_millis(5)).await;
+    }
+
+    // Assert
+    handle.await.expect("writer should complete")?;
+    for item in reads {
+        let item = item.expect("item should exist");
+        assert_eq!(item.len(), 100_000);
+        assert!(item.chars().all(|character| item.starts_with(character)));
+    }
+    Ok(())
+}
+
+/// Hold a lock in a child process started by [`spawn_lock_holder`].
+///
+/// Does nothing when run as part of the test suite.
+#[tokio::test]
+async fn child_process_holds_lock() -> Result<(), Failure<TableAction>> {
+    let Ok(path) = env::var(CHILD_LOCK_PATH) else {
+        return Ok(());
+    };
+    let path = PathBuf::from(path);
+    let options = LockOptions {
+        advisory: true,
+        ..LockOptions::default()
+    };
+    let _guard = if env::var(CHILD_LOCK_SHARED).is_ok() {
+        acquire_shared_lock(&path, &options).await?
+    } else {
+        acquire_lock(&path, &options).await?
+    };
+    write(path.with_extension("ready"), "").expect("should write ready file");
+    sleep(CHILD_HOLD_DURATION).await;
+    Ok(())
+}
+
+const CHILD_LOCK_PATH: &str = "FLAT_DB_TEST_CHILD_LOCK_PATH";
+const CHILD_LOCK_SHARED: &str = "FLAT_DB_TEST_CHILD_LOCK_SHARED";
+const CHILD_HOLD_DURATION: Duration = Duration::from_millis(500);
+
+/// Start a copy of the test binary that holds a lock on `path`.
+///
+/// Returns once the lock is held.
+async fn spawn_lock_holder(path: &Path, is_shared: bool) -> Child {
+    let mut command = Command::new(env::current_exe().expect("should get test binary"));
+    command
+        .args([
+            "--exact",
+            "tests::lock_guard_tests::child_process_holds_lock",
+            "--nocapture",
+        ])
+        .env(CHILD_LOCK_PATH, path)
+        .kill_on_drop(true);
+    if is_shared {
+        command.env(CHILD_LOCK_SHARED, "1");
+    }
+    let child = command.spawn().expect("should spawn child process");
+    let ready = path.with_extension("ready");
+    let start = Instant::now();
+    while !ready.exists() {
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "child process should acquire lock"
+        );
+        sleep(Duration::from_millis(10)).await;
+    }
+    child
+}
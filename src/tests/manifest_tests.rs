@@ -0,0 +1,151 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::error::Error;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn open_writes_manifest() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+
+    // Act
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+
+    // Assert
+    let manifest = table.read_manifest().await?.expect("should exist");
+    assert_eq!(manifest, table.manifest());
+    assert_eq!(manifest.key_width, 20);
+    assert_eq!(manifest.chunk_width, 1);
+    assert_eq!(manifest.format, "yml");
+    assert!(test_dir.path.join("flat_db.yml").is_file());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn open_existing_table() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+    table.set_many(example_items(), true).await?;
+
+    // Act
+    let reopened = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+
+    // Assert
+    assert_eq!(reopened.get_all().await?, example_items());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn open_fails_with_different_chunk_width() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let _table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+
+    // Act
+    let result = Table::<20, 2, ExampleItem>::open(test_dir.path.clone()).await;
+
+    // Assert
+    let error = result.err().expect("should fail");
+    assert_eq!(error.action(), &TableAction::Open);
+    let source = error.source().expect("should have source");
+    assert_eq!(
+        source.downcast_ref::<ManifestError>(),
+        Some(&ManifestError::Mismatch {
+            field: "chunk_width",
+            expected: "2".to_owned(),
+            actual: "1".to_owned(),
+        })
+    );
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn open_without_manifest_fails_with_different_chunk_width() -> Result<(), Failure<TableAction>>
+{
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    table.set_many(example_items(), true).await?;
+
+    // Act
+    let result = Table::<20, 2, ExampleItem>::open(test_dir.path.clone()).await;
+    let reopened = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+
+    // Assert
+    let error = result.err().expect("should fail");
+    let source = error.source().expect("should have source");
+    assert_eq!(
+        source.downcast_ref::<ManifestError>(),
+        Some(&ManifestError::Mismatch {
+            field: "chunk_width",
+            expected: "2".to_owned(),
+            actual: "1".to_owned(),
+        })
+    );
+    assert_eq!(reopened.get_all().await?, example_items());
+    assert!(reopened.read_manifest().await?.is_some());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn open_fails_with_different_schema() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let _table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .with_schema("example", 1)
+        .init()
+        .await?;
+
+    // Act
+    let result = Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .with_schema("other", 1)
+        .init()
+        .await;
+
+    // Assert
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn open_ignores_schema_name_if_not_set() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let _table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .with_schema("flat_db::tests::example_item::ExampleItem", 0)
+        .init()
+        .await?;
+
+    // Act
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await;
+
+    // Assert
+    assert!(table.is_ok());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn reshard_updates_manifest() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+    table.set_many(example_items(), true).await?;
+
+    // Act
+    table.reshard::<2>().await?;
+
+    // Assert
+    let reopened = Table::<20, 2, ExampleItem>::open(test_dir.path.clone()).await?;
+    assert_eq!(reopened.get_all().await?, example_items());
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::tests::helpers::create_hash;
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fs::write;
+use tracing_test::traced_test;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ItemV0 {
+    done: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ItemV1 {
+    success: bool,
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_on_read() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = create_v0_table(&test_dir).await?;
+
+    // Act
+    let table = open_v1_table(&test_dir).await?;
+    let output = table.get_all().await?;
+
+    // Assert
+    assert_eq!(output.len(), items.len());
+    for (hash, item) in items {
+        assert_eq!(output.get(&hash), Some(&ItemV1 { success: item.done }));
+    }
+    let manifest = table.read_manifest().await?.expect("should exist");
+    assert_eq!(manifest.version, 0);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_write_before_migrate_all() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = create_v0_table(&test_dir).await?;
+    let table = open_v1_table(&test_dir).await?;
+    let hash = create_hash(0x10, 0x05);
+
+    // Act
+    table.set(hash, ItemV1 { success: true }).await?;
+
+    // Assert
+    let output = table.get_all().await?;
+    assert_eq!(output.len(), items.len() + 1);
+    assert_eq!(output.get(&hash), Some(&ItemV1 { success: true }));
+    let manifest = table.read_manifest().await?.expect("should exist");
+    assert_eq!(manifest.chunk_versions.get("10"), Some(&1));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_migrate_all() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = create_v0_table(&test_dir).await?;
+    let table = open_v1_table(&test_dir).await?;
+    let mut progress = Vec::new();
+
+    // Act
+    let migrated = table.migrate_all(|p| progress.push(p)).await?;
+
+    // Assert
+    assert_eq!(migrated, items.len());
+    assert_eq!(progress.len(), 2);
+    assert!(progress.iter().all(|p| p.total == 2));
+    let manifest = table.read_manifest().await?.expect("should exist");
+    assert_eq!(manifest.version, 1);
+    assert!(manifest.chunk_versions.is_empty());
+    let current = Table::<20, 1, ItemV1>::new(test_dir.path.clone())
+        .with_schema("item", 1)
+        .init()
+        .await?;
+    assert_eq!(current.get_all().await?, table.get_all().await?);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_stops_reading_manifest_once_complete() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let items = create_v0_table(&test_dir).await?;
+    let table = open_v1_table(&test_dir).await?;
+    table.migrate_all(|_| {}).await?;
+    write(test_dir.path.join("flat_db.yml"), "not: [a manifest").expect("should write");
+
+    // Act
+    let output = table.get_all().await?;
+
+    // Assert
+    assert_eq!(output.len(), items.len());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_failure_names_chunk_and_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    create_v0_table(&test_dir).await?;
+    let table = Table::<20, 1, ItemV1>::new(test_dir.path.clone())
+        .with_schema("item", 0)
+        .with_migrations(Migrations::new().with_step(|value| value))
+        .init()
+        .await?;
+
+    // Act
+    let result = table.get(create_hash(0x12, 0x01)).await;
+
+    // Assert
+    let error = result.expect_err("should fail");
+    assert_eq!(error.action(), &TableAction::Get);
+    let debug = format!("{error:?}");
+    assert!(debug.contains("Migrate"));
+    assert!(debug.contains(&create_hash(0x12, 0x01).to_hex()));
+    assert!(debug.contains("12.yml"));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn migration_open_fails_with_newer_version() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    create_v0_table(&test_dir).await?;
+    open_v1_table(&test_dir).await?.migrate_all(|_| {}).await?;
+
+    // Act
+    let result = Table::<20, 1, ItemV0>::new(test_dir.path.clone())
+        .with_schema("item", 0)
+        .init()
+        .await;
+
+    // Assert
+    assert!(result.is_err());
+    Ok(())
+}
+
+async fn create_v0_table(
+    test_dir: &TestDirectory,
+) -> Result<BTreeMap<Hash<20>, ItemV0>, Failure<TableAction>> {
+    let table = Table::<20, 1, ItemV0>::new(test_dir.path.clone())
+        .with_schema("item", 0)
+        .init()
+        .await?;
+    let mut items = BTreeMap::new();
+    for (first, second) in [(0x12, 0x01), (0x12, 0x02), (0x34, 0x01)] {
+        items.insert(
+            create_hash(first, second),
+            ItemV0 {
+                done: second % 2 == 0,
+            },
+        );
+    }
+    table.set_many(items.clone(), true).await?;
+    Ok(items)
+}
+
+async fn open_v1_table(
+    test_dir: &TestDirectory,
+) -> Result<Table<20, 1, ItemV1>, Failure<TableAction>> {
+    Table::<20, 1, ItemV1>::new(test_dir.path.clone())
+        .with_schema("item", 0)
+        .with_migrations(Migrations::new().with_step(rename_done))
+        .init()
+        .await
+}
+
+fn rename_done(mut value: Value) -> Value {
+    if let Some(mapping) = value.as_mapping_mut()
+        && let Some(done) = mapping.remove("done")
+    {
+        mapping.insert("success".into(), done);
+    }
+    value
+}
@@ -1,8 +1,28 @@
+mod atomic_file_tests;
+mod cache_tests;
+#[cfg(any(feature = "zstd", feature = "gzip"))]
+mod compression_tests;
 mod example_item;
+mod exclusive_tests;
 mod file_table_tests;
+mod formats_tests;
 mod hash_tests;
 mod helpers;
+mod index_tests;
+mod keys_tests;
+mod lenient_tests;
 mod lock_guard_tests;
+mod manifest_tests;
+mod migration_tests;
+mod reshard_tests;
+mod scan_tests;
+mod sidecar_tests;
 mod snapshots;
+mod stream_tests;
 mod table_tests;
 mod test_directory;
+mod transaction_tests;
+mod update_tests;
+mod verify_tests;
+mod version_tests;
+mod wal_tests;
@@ -0,0 +1,158 @@
+use crate::lock_guard::{acquire_exclusive_lock, acquire_writer_lock};
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::fs::{create_dir_all, read_dir, remove_file, write};
+use std::time::Duration;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn table_reshard_to_wider_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+
+    // Act
+    let resharded = table.reshard::<2>().await?;
+
+    // Assert
+    assert_eq!(resharded.get_all().await?, items);
+    assert!(table.get_all().await?.is_empty());
+    let file_names: Vec<_> = read_dir(&test_dir.path)
+        .expect("should read dir")
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(file_names.len(), items.len());
+    assert!(file_names.iter().all(|name| name.len() == "1924.yml".len()));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn table_reshard_to_narrower_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 2, ExampleItem>::new(test_dir.path.clone());
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+
+    // Act
+    let resharded = table.reshard::<1>().await?;
+
+    // Assert
+    assert_eq!(resharded.get_all().await?, items);
+    assert_eq!(resharded.list_chunks().await?.len(), 3);
+    assert!(table.list_chunks().await?.is_empty());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn table_reshard_resumes_after_interruption() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let wider = Table::<20, 2, ExampleItem>::new(test_dir.path.clone());
+    wider.set_many(items.clone(), true).await?;
+    let removed = table
+        .list_chunks()
+        .await?
+        .into_iter()
+        .next()
+        .expect("should have a chunk");
+    for path in table.find_chunk_paths(removed) {
+        remove_file(path).expect("should remove chunk");
+    }
+
+    // Act
+    let resharded = table.reshard::<2>().await?;
+
+    // Assert
+    assert_eq!(resharded.get_all().await?, items);
+    assert!(table.list_chunks().await?.is_empty());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_reshard_to_wider_chunks() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = FileTable::<20, 1>::new(test_dir.path.clone(), "txt");
+    for hash in example_items().into_keys() {
+        let path = table.get_path(hash);
+        create_dir_all(path.parent().expect("should have parent")).expect("should create dir");
+        write(&path, hash.to_hex()).expect("should write file");
+    }
+    let expected = table.get_all().await?.len();
+
+    // Act
+    let resharded = table.reshard::<2>().await?;
+
+    // Assert
+    assert_eq!(resharded.get_all().await?.len(), expected);
+    assert!(table.get_all().await?.is_empty());
+    assert!(!test_dir.path.join("19").exists());
+    assert!(test_dir.path.join("1924").is_dir());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_reshard_waits_for_writers() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table =
+        FileTable::<20, 1>::new(test_dir.path.clone(), "txt").with_lock_options(LockOptions {
+            timeout: Duration::from_millis(200),
+            ..LockOptions::default()
+        });
+    let writer = acquire_writer_lock(&table.get_table_lock_path(), &table.lock_options)
+        .await
+        .map_err(Failure::wrap(FileTableAction::AcquireLock))?;
+
+    // Act
+    let blocked = table.reshard::<2>().await;
+    drop(writer);
+    let resharded = table.reshard::<2>().await;
+
+    // Assert
+    assert!(blocked.is_err());
+    assert!(resharded.is_ok());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn file_table_set_waits_for_reshard() -> Result<(), Failure<FileTableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table =
+        FileTable::<20, 1>::new(test_dir.path.clone(), "txt").with_lock_options(LockOptions {
+            fail_fast: true,
+            ..LockOptions::default()
+        });
+    let source = test_dir.path.join("source.txt");
+    write(&source, "content").expect("should write file");
+    let hash = *example_items().keys().next().expect("should have an item");
+    let exclusive = acquire_exclusive_lock(&table.get_table_lock_path(), &table.lock_options)
+        .await
+        .map_err(Failure::wrap(FileTableAction::AcquireLock))?;
+
+    // Act
+    let blocked = table.set(hash, &source).await;
+    drop(exclusive);
+    table.set(hash, &source).await?;
+
+    // Assert
+    assert!(blocked.is_err());
+    assert!(table.contains(hash));
+    Ok(())
+}
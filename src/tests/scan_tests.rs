@@ -0,0 +1,114 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_hash;
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::error::Error;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn scan_prefix() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table().await?;
+
+    // Act
+    let chunk = table.scan_prefix(&[0x89]).await?;
+    let single = table.scan_prefix(&[0x89, 0x9f]).await?;
+    let none = table.scan_prefix(&[0x90]).await?;
+
+    // Assert
+    assert_eq!(chunk.len(), 3);
+    assert!(chunk.keys().all(|hash| hash.as_bytes()[0] == 0x89));
+    assert_eq!(
+        single.keys().collect::<Vec<_>>(),
+        vec![&create_hash(0x89, 0x9f)]
+    );
+    assert!(none.is_empty());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn scan_range() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table().await?;
+
+    // Act
+    let output = table
+        .range(create_hash(0x19, 0x2f)..create_hash(0x89, 0x9f))
+        .await?;
+
+    // Assert
+    let expected = vec![
+        create_hash(0x19, 0x2f),
+        create_hash(0x19, 0x3a),
+        create_hash(0x89, 0x94),
+    ];
+    assert_eq!(output.into_keys().collect::<Vec<_>>(), expected);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn scan_find_by_abbrev() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table().await?;
+
+    // Act
+    let found = table.find_by_abbrev("89A").await?;
+    let missing = table.find_by_abbrev("ff").await?;
+
+    // Assert
+    let (hash, item) = found.expect("should find item");
+    assert_eq!(hash, create_hash(0x89, 0xaa));
+    assert_eq!(item.hash, hash);
+    assert!(missing.is_none());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn scan_find_by_abbrev_ambiguous() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table().await?;
+
+    // Act
+    let ambiguous = table
+        .find_by_abbrev("899")
+        .await
+        .expect_err("should be ambiguous");
+    let invalid = table
+        .find_by_abbrev("zz")
+        .await
+        .expect_err("should be invalid");
+
+    // Assert
+    assert_eq!(ambiguous.action(), &TableAction::FindByAbbrev);
+    assert_eq!(
+        ambiguous
+            .source()
+            .and_then(|source| source.downcast_ref::<ScanError>()),
+        Some(&ScanError::Ambiguous {
+            abbrev: "899".to_owned(),
+            count: 2,
+        })
+    );
+    assert_eq!(
+        invalid
+            .source()
+            .and_then(|source| source.downcast_ref::<ScanError>()),
+        Some(&ScanError::InvalidAbbrev {
+            abbrev: "zz".to_owned(),
+        })
+    );
+    Ok(())
+}
+
+async fn create_table() -> Result<(TestDirectory, Table<20, 1, ExampleItem>), Failure<TableAction>>
+{
+    let test_dir = TestDirectory::new();
+    let table = Table::new(test_dir.path.clone());
+    table.set_many(example_items(), false).await?;
+    Ok((test_dir, table))
+}
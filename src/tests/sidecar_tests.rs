@@ -0,0 +1,161 @@
+use crate::table::get_chunk_hash;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::{create_table, rewrite_keeping_length_and_modified_time};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::cell::Cell;
+use std::fs::remove_dir_all;
+use std::path::PathBuf;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn sidecar_written_with_chunk() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let table = table.with_sidecars(true);
+    let items = example_items();
+    let (hash, item) = items.first_key_value().expect("should have an item");
+
+    // Act
+    table.set_many(items.clone(), true).await?;
+    let found = table.get(*hash).await?;
+
+    // Assert
+    assert_eq!(found.as_ref(), Some(item));
+    assert!(get_sidecar_path(&test_dir, *hash).is_file());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn sidecar_skips_parsing_chunk_for_missing_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_sidecars(true);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    let missing = get_missing_hash(existing);
+    table.get(existing).await?;
+    let count_reads = || {
+        let count = Cell::new(0);
+        logs_assert(|lines| {
+            count.set(
+                lines
+                    .iter()
+                    .filter(|line| line.contains("Reading chunk"))
+                    .count(),
+            );
+            Ok(())
+        });
+        count.get()
+    };
+    let reads_before = count_reads();
+
+    // Act
+    let get_missing = table.get(missing).await?;
+    let contains_missing = table.contains(missing).await?;
+
+    // Assert
+    assert_eq!(get_missing, None);
+    assert!(!contains_missing);
+    assert!(reads_before > 0);
+    assert_eq!(count_reads(), reads_before);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn sidecar_regenerated_after_rewrite_keeping_length_and_modified_time()
+-> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_sidecars(true);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    let renamed = get_missing_hash(existing);
+    table.get(existing).await?;
+    let path = table.get_chunk_path(get_chunk_hash(existing));
+    rewrite_keeping_length_and_modified_time(&path, |yaml| {
+        yaml.replace(&existing.to_hex(), &renamed.to_hex())
+    });
+
+    // Act
+    let found = table.get(renamed).await?;
+    let contains = table.contains(renamed).await?;
+
+    // Assert
+    assert!(found.is_some());
+    assert!(contains);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn sidecar_regenerated_after_chunk_written_elsewhere() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let table = table.with_sidecars(true);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    let added = get_missing_hash(existing);
+    let item = ExampleItem {
+        hash: added,
+        success: true,
+        optional: None,
+    };
+    table.get(existing).await?;
+    Table::<20, 1, ExampleItem>::new(test_dir.path.clone())
+        .set(added, item.clone())
+        .await?;
+
+    // Act
+    let found = table.get(added).await?;
+    let contains = table.contains(added).await?;
+
+    // Assert
+    assert_eq!(found, Some(item));
+    assert!(contains);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn sidecar_regenerated_after_removal() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    let table = table.with_sidecars(true);
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let existing = *items.keys().next().expect("should have an item");
+    remove_dir_all(test_dir.path.join(".sidecars")).expect("should remove sidecars");
+
+    // Act
+    let found = table.contains(existing).await?;
+    let missing = table.get(get_missing_hash(existing)).await?;
+
+    // Assert
+    assert!(found);
+    assert_eq!(missing, None);
+    assert!(get_sidecar_path(&test_dir, existing).is_file());
+    Ok(())
+}
+
+fn get_sidecar_path(test_dir: &TestDirectory, hash: Hash<20>) -> PathBuf {
+    let chunk_hash: Hash<1> = get_chunk_hash(hash);
+    test_dir
+        .path
+        .join(".sidecars")
+        .join(format!("{chunk_hash}.keys"))
+}
+
+/// Get a hash in the same chunk as `hash` that is not an example item.
+fn get_missing_hash(hash: Hash<20>) -> Hash<20> {
+    let mut bytes = *hash.as_bytes();
+    bytes[19] = bytes[19].wrapping_add(1);
+    Hash::new(bytes)
+}
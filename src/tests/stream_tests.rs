@@ -0,0 +1,81 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
+use crate::*;
+use futures::{StreamExt, TryStreamExt};
+use rogue_logging::Failure;
+use std::collections::BTreeMap;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn stream_items() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+
+    // Act
+    let output: Vec<(Hash<20>, ExampleItem)> = table.stream().try_collect().await?;
+
+    // Assert
+    assert_eq!(output, items.into_iter().collect::<Vec<_>>());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn stream_keys() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+
+    // Act
+    let output: Vec<Hash<20>> = table.keys().try_collect().await?;
+
+    // Assert
+    assert_eq!(output, items.into_keys().collect::<Vec<_>>());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn stream_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    table.set_many(example_items(), false).await?;
+    let table = table.with_read_ahead(1);
+
+    // Act
+    let output: BTreeMap<Hash<1>, usize> = table
+        .chunks()
+        .map_ok(|(chunk_hash, chunk)| (chunk_hash, chunk.len()))
+        .try_collect()
+        .await?;
+
+    // Assert
+    let chunks: Vec<String> = output.keys().map(Hash::to_hex).collect();
+    assert_eq!(chunks, vec!["19", "89", "ac"]);
+    assert!(output.values().all(|count| *count == 3));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn stream_missing_directory() {
+    // Arrange
+    let (test_dir, _) = create_table();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.join("missing"));
+
+    // Act
+    let output: Vec<_> = table.stream().collect().await;
+
+    // Assert
+    assert_eq!(output.len(), 1);
+    let error = output
+        .into_iter()
+        .next()
+        .expect("should have an item")
+        .expect_err("should fail");
+    assert_eq!(error.action(), &TableAction::ReadDir);
+}
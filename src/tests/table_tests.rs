@@ -1,11 +1,11 @@
+use crate::table::get_chunk_hash;
 use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
 use crate::tests::snapshots::TableSnapshot;
-use crate::tests::test_directory::TestDirectory;
-use crate::{Hash, Table, TableAction};
+use crate::{Hash, TableAction};
 use rogue_logging::Failure;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::create_dir_all;
-use std::marker::PhantomData;
 use tokio::runtime::Runtime;
 use tracing_test::traced_test;
 
@@ -26,6 +26,56 @@ async fn table_set_many_and_get_all() -> Result<(), Failure<TableAction>> {
     Ok(())
 }
 
+#[traced_test]
+#[tokio::test]
+async fn table_set_many_with_concurrency_of_one() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_concurrency(1);
+    let items = example_items();
+    let expected_count = items.len();
+    let chunk_locks: BTreeSet<String> = items
+        .keys()
+        .map(|hash| {
+            let mut lock = table.get_base_chunk_path(get_chunk_hash(*hash));
+            lock.set_extension("lock");
+            lock.display().to_string()
+        })
+        .collect();
+
+    // Act
+    let added = table.set_many(items, true).await?;
+    let items: BTreeMap<Hash<20>, ExampleItem> = table.get_all().await?;
+
+    // Assert
+    assert!(chunk_locks.len() > 1);
+    assert_eq!(added, expected_count);
+    assert_eq!(items.len(), expected_count);
+    logs_assert(|lines| {
+        let mut held = 0_usize;
+        let mut peak = 0;
+        for line in lines {
+            if !chunk_locks.iter().any(|lock| line.contains(lock.as_str())) {
+                continue;
+            }
+            if line.contains("Lock acquired") {
+                held += 1;
+                peak = peak.max(held);
+            } else if line.contains("Lock released") {
+                held = held.saturating_sub(1);
+            }
+        }
+        if peak == 1 {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected at most one chunk lock held, found {peak}"
+            ))
+        }
+    });
+    Ok(())
+}
+
 #[traced_test]
 #[tokio::test]
 async fn table_set_and_remove() -> Result<(), Failure<TableAction>> {
@@ -149,13 +199,52 @@ async fn table_remove_missing_item() -> Result<(), Failure<TableAction>> {
     Ok(())
 }
 
-fn create_table() -> (TestDirectory, Table<20, 1, ExampleItem>) {
-    let test_dir = TestDirectory::new();
-    let table = Table::<20, 1, ExampleItem> {
-        directory: test_dir.path.clone(),
-        phantom: PhantomData,
-    };
-    (test_dir, table)
+#[traced_test]
+#[tokio::test]
+async fn table_get_many_returns_every_requested_hash() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let (missing_hash, _) = create_single_item();
+    let hashes: Vec<Hash<20>> = items.keys().copied().chain([missing_hash]).collect();
+
+    // Act
+    let result = table.get_many(hashes).await?;
+
+    // Assert
+    assert_eq!(result.len(), items.len() + 1);
+    assert_eq!(result.get(&missing_hash), Some(&None));
+    for (hash, item) in items {
+        assert_eq!(result.get(&hash), Some(&Some(item)));
+    }
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn table_remove_many() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), true).await?;
+    let (missing_hash, _) = create_single_item();
+    let removed_hashes: Vec<Hash<20>> = items.keys().copied().step_by(2).collect();
+    let expected_removed = removed_hashes.len();
+
+    // Act
+    let removed = table
+        .remove_many(removed_hashes.iter().copied().chain([missing_hash]))
+        .await?;
+
+    // Assert
+    assert_eq!(removed, expected_removed);
+    let items_after: BTreeMap<Hash<20>, ExampleItem> = table.get_all().await?;
+    assert_eq!(items_after.len(), items.len() - expected_removed);
+    for hash in removed_hashes {
+        assert!(!items_after.contains_key(&hash));
+    }
+    Ok(())
 }
 
 fn create_single_item() -> (Hash<20>, ExampleItem) {
@@ -0,0 +1,128 @@
+use crate::lock_guard::acquire_lock;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::fs::{copy, create_dir_all, read_dir, write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn transaction_commit() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.clone()).await?;
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    let mut entries = items.into_iter();
+    let (removed, _) = entries.next().expect("should have an item");
+    let (replaced, item) = entries.next_back().expect("should have an item");
+    let replacement = ExampleItem {
+        optional: Some("Replaced".to_owned()),
+        ..item
+    };
+
+    // Act
+    let mut transaction = table.transaction();
+    transaction
+        .remove(removed)
+        .set(replaced, replacement.clone());
+    let chunks = transaction.commit().await?;
+
+    // Assert
+    assert_eq!(chunks, 2);
+    assert_eq!(table.get(removed).await?, None);
+    assert_eq!(table.get(replaced).await?, Some(replacement));
+    assert_eq!(table.get_all().await?.len(), 8);
+    let mut journals = read_dir(test_dir.path.join(".journal")).expect("should read journals");
+    assert!(journals.next().is_none());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn transaction_commit_empty() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+
+    // Act
+    let chunks = table.transaction().commit().await?;
+
+    // Assert
+    assert_eq!(chunks, 0);
+    assert!(table.get_all().await?.is_empty());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn transaction_recover_committed() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let journal = create_journal(&test_dir).await?;
+    write(journal.join("COMMIT"), "").expect("should write marker");
+
+    // Act
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.join("table")).await?;
+
+    // Assert
+    assert_eq!(table.get_all().await?.len(), 3);
+    assert!(!journal.exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn transaction_recover_uncommitted() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let journal = create_journal(&test_dir).await?;
+
+    // Act
+    let table = Table::<20, 1, ExampleItem>::open(test_dir.path.join("table")).await?;
+
+    // Assert
+    assert!(table.get_all().await?.is_empty());
+    assert!(!journal.exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn transaction_recover_skips_journal_in_progress() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let journal = create_journal(&test_dir).await?;
+    let _journal_lock = acquire_lock(&journal, &LockOptions::default()).await?;
+    let options = LockOptions {
+        timeout: Duration::from_secs(10),
+        ..LockOptions::default()
+    };
+    let started = Instant::now();
+
+    // Act
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.join("table"))
+        .with_lock_options(options)
+        .init()
+        .await?;
+
+    // Assert
+    assert!(started.elapsed() < options.timeout);
+    assert!(table.get_all().await?.is_empty());
+    assert!(journal.exists());
+    Ok(())
+}
+
+/// Create a journal for the `table` directory staging the `19` chunk.
+async fn create_journal(test_dir: &TestDirectory) -> Result<PathBuf, Failure<TableAction>> {
+    let source = Table::<20, 1, ExampleItem>::new(test_dir.path.join("source"));
+    create_dir_all(&source.directory).expect("should create dir");
+    source.set_many(example_items(), false).await?;
+    let journal = test_dir.path.join("table").join(".journal").join("1-1-0");
+    create_dir_all(&journal).expect("should create dir");
+    copy(source.directory.join("19.yml"), journal.join("19.yml")).expect("should copy chunk");
+    Ok(journal)
+}
@@ -0,0 +1,158 @@
+use crate::table::get_chunk_hash;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
+use crate::*;
+use rogue_logging::Failure;
+use std::collections::BTreeSet;
+use std::fs::write;
+use tokio::task::JoinSet;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn update_existing_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+
+    // Act
+    let (old, new) = table
+        .update(hash, |item| {
+            item.map(|item| ExampleItem {
+                optional: Some("Updated".to_owned()),
+                ..item
+            })
+        })
+        .await?;
+
+    // Assert
+    assert_eq!(old, Some(item));
+    assert_eq!(
+        new.as_ref().and_then(|item| item.optional.as_deref()),
+        Some("Updated")
+    );
+    assert_eq!(table.get(hash).await?, new);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn update_returning_none_removes() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+
+    // Act
+    let (old, new) = table.update(hash, |_| None).await?;
+
+    // Assert
+    assert_eq!(old, Some(item));
+    assert_eq!(new, None);
+    assert_eq!(table.get(hash).await?, None);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn update_concurrent() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table
+        .set(
+            hash,
+            ExampleItem {
+                optional: Some(String::new()),
+                ..item
+            },
+        )
+        .await?;
+
+    // Act
+    let mut tasks = JoinSet::new();
+    for _ in 0..8 {
+        let table = table.clone();
+        tasks.spawn(async move {
+            table
+                .update(hash, |item| {
+                    item.map(|mut item| {
+                        item.optional.get_or_insert_default().push('x');
+                        item
+                    })
+                })
+                .await
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        result.expect("task should complete")?;
+    }
+
+    // Assert
+    let item = table.get(hash).await?.expect("should exist");
+    assert_eq!(item.optional.as_deref(), Some("xxxxxxxx"));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn update_many_across_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    let missing = Hash::new([0xff; 20]);
+    let hashes = items.keys().copied().chain([missing]);
+
+    // Act
+    let results = table
+        .update_many(hashes, |_, item| item.filter(|item| item.success))
+        .await?;
+
+    // Assert
+    assert_eq!(results.len(), items.len() + 1);
+    assert_eq!(results.get(&missing), Some(&(None, None)));
+    let remaining = table.get_all().await?;
+    assert!(remaining.values().all(|item| item.success));
+    assert_eq!(
+        remaining.len(),
+        items.values().filter(|item| item.success).count()
+    );
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn update_many_reports_failed_chunks() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let table = table.with_concurrency(2).with_lock_options(LockOptions {
+        fail_fast: true,
+        ..LockOptions::default()
+    });
+    let items = example_items();
+    table.set_many(items.clone(), false).await?;
+    let chunk_count = items
+        .keys()
+        .map(|hash| get_chunk_hash::<20, 1>(*hash))
+        .collect::<BTreeSet<_>>()
+        .len();
+    let locked_hash = *items.keys().next().expect("should have an item");
+    let mut lock_path = table.get_base_chunk_path(get_chunk_hash(locked_hash));
+    lock_path.set_extension("lock");
+    write(&lock_path, "").expect("should write lock");
+
+    // Act
+    let error = table
+        .update_many(items.keys().copied(), |_, _| None)
+        .await
+        .expect_err("should fail");
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::UpdateMany);
+    assert_eq!(error.get("failed"), Some("1".to_owned()));
+    assert_eq!(error.get("succeeded"), Some((chunk_count - 1).to_string()));
+    assert!(table.get(locked_hash).await?.is_some());
+    Ok(())
+}
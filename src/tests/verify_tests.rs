@@ -0,0 +1,148 @@
+use crate::table::get_chunk_hash;
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::helpers::create_table;
+use crate::*;
+use rogue_logging::Failure;
+use std::fs::{create_dir_all, write};
+use std::path::Path;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn verify_clean_table() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let items = example_items();
+    table.set_many(items, true).await?;
+
+    // Act
+    let report = table.verify().await?;
+
+    // Assert
+    assert!(report.is_ok());
+    assert!(report.chunks > 0);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn verify_reports_misplaced_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let hash = Hash::<20>::new([0xab; 20]);
+    let wrong = table.get_chunk_path(Hash::new([0x00]));
+    write_yaml(&table, &wrong, &item_yaml(hash));
+
+    // Act
+    let report = table.verify().await?;
+    let found = table.get(hash).await?;
+
+    // Assert
+    assert!(!report.is_ok());
+    assert_eq!(
+        report.misplaced,
+        vec![MisplacedItem {
+            key: hash,
+            path: wrong,
+            expected_path: table.get_chunk_path(get_chunk_hash(hash)),
+        }]
+    );
+    assert_eq!(found, None);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn repair_moves_misplaced_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let hash = Hash::<20>::new([0xab; 20]);
+    let other = Hash::<20>::new([0x00; 20]);
+    let wrong = table.get_chunk_path(get_chunk_hash(other));
+    let yaml = format!("{}{}", item_yaml(hash), item_yaml(other));
+    write_yaml(&table, &wrong, &yaml);
+
+    // Act
+    let moved = table.repair().await?;
+    let report = table.verify().await?;
+
+    // Assert
+    assert_eq!(moved, 1);
+    assert!(report.is_ok());
+    assert_eq!(table.get(hash).await?.map(|item| item.hash), Some(hash));
+    assert_eq!(table.get(other).await?.map(|item| item.hash), Some(other));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn verify_reports_invalid_chunk_name() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    table.set_many(example_items(), true).await?;
+    let path = test_dir.path.join("not-a-chunk.yml");
+    write(&path, "{}").expect("should write file");
+    write(test_dir.path.join("notes.txt"), "ignored").expect("should write file");
+
+    // Act
+    let report = table.verify().await?;
+
+    // Assert
+    assert_eq!(report.invalid_chunk_names, vec![path]);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn verify_reports_locks_and_temp_files() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table();
+    table.set_many(example_items(), true).await?;
+    let lock = test_dir.path.join("ab.lock");
+    let temp = test_dir.path.join("ab.yml.tmp");
+    write(&lock, "").expect("should write lock");
+    write(&temp, "").expect("should write temp file");
+
+    // Act
+    let report = table.verify().await?;
+
+    // Assert
+    assert_eq!(report.locks, vec![lock]);
+    assert_eq!(report.temp_files, vec![temp]);
+    assert!(report.invalid_chunk_names.is_empty());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn verify_reports_duplicate_keys() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table();
+    let hash = Hash::<20>::new([0xab; 20]);
+    let path = table.get_chunk_path(get_chunk_hash(hash));
+    let yaml = format!("{}{}", item_yaml(hash), item_yaml(hash));
+    write_yaml(&table, &path, &yaml);
+
+    // Act
+    let report = table.verify().await?;
+
+    // Assert
+    assert_eq!(
+        report.duplicate_keys,
+        vec![DuplicateKey {
+            path,
+            key: hash.to_hex(),
+            count: 2,
+        }]
+    );
+    Ok(())
+}
+
+fn item_yaml(hash: Hash<20>) -> String {
+    format!("{hash}:\n  hash: {hash}\n  success: true\n  optional: null\n")
+}
+
+fn write_yaml(table: &Table<20, 1, ExampleItem>, path: &Path, yaml: &str) {
+    create_dir_all(&table.directory).expect("should create dir");
+    write(path, yaml).expect("should write chunk");
+}
@@ -0,0 +1,125 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use tokio::task::JoinSet;
+use tracing_test::traced_test;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    value: u32,
+}
+
+#[traced_test]
+#[tokio::test]
+async fn compare_and_set_unchanged() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+    let (_, version) = table.get_versioned(hash).await?.expect("should exist");
+    let replacement = ExampleItem {
+        success: !item.success,
+        ..item
+    };
+
+    // Act
+    let new_version = table
+        .compare_and_set(hash, Some(version), replacement.clone())
+        .await?;
+
+    // Assert
+    assert_ne!(new_version, version);
+    assert_eq!(
+        table.get_versioned(hash).await?,
+        Some((replacement, new_version))
+    );
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn compare_and_set_conflict() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+    let (_, version) = table.get_versioned(hash).await?.expect("should exist");
+    let intervening = ExampleItem {
+        optional: Some("Intervening".to_owned()),
+        ..item.clone()
+    };
+    table.set(hash, intervening.clone()).await?;
+
+    // Act
+    let error = table
+        .compare_and_set(hash, Some(version), item)
+        .await
+        .expect_err("should conflict");
+
+    // Assert
+    assert_eq!(error.action(), &TableAction::CompareAndSet);
+    let source = error.source().expect("should have source");
+    assert!(source.downcast_ref::<ConflictError>().is_some());
+    assert_eq!(table.get(hash).await?, Some(intervening));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn compare_and_set_expect_missing() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+
+    // Act
+    let created = table.compare_and_set(hash, None, item.clone()).await;
+    let duplicate = table.compare_and_set(hash, None, item).await;
+
+    // Assert
+    assert!(created.is_ok());
+    assert!(duplicate.is_err());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn compare_and_set_concurrent_workers() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let test_dir = TestDirectory::new();
+    let table = Table::<20, 1, Counter>::new(test_dir.path.clone());
+    let hash = Hash::new([0x42; 20]);
+    table.set(hash, Counter { value: 0 }).await?;
+    let workers = 8;
+
+    // Act
+    let mut tasks = JoinSet::new();
+    for _ in 0..workers {
+        let table = table.clone();
+        tasks.spawn(async move {
+            loop {
+                let (counter, version) = table.get_versioned(hash).await?.expect("should exist");
+                let next = Counter {
+                    value: counter.value + 1,
+                };
+                match table.compare_and_set(hash, Some(version), next).await {
+                    Ok(_) => return Ok::<(), Failure<TableAction>>(()),
+                    Err(error) if error.action() == &TableAction::CompareAndSet => {}
+                    Err(error) => return Err(error),
+                }
+            }
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        result.expect("task should complete")?;
+    }
+
+    // Assert
+    assert_eq!(table.get(hash).await?, Some(Counter { value: workers }));
+    Ok(())
+}
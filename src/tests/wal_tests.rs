@@ -0,0 +1,273 @@
+use crate::tests::example_item::{ExampleItem, example_items};
+use crate::tests::test_directory::TestDirectory;
+use crate::*;
+use rogue_logging::Failure;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test]
+async fn wal_set_and_remove() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let items = example_items();
+    let (removed, _) = items.first_key_value().expect("should have an item");
+
+    // Act
+    for (hash, item) in items.clone() {
+        table.set(hash, item).await?;
+    }
+    let output = table.remove(*removed).await?;
+
+    // Assert
+    assert_eq!(output.as_ref(), items.get(removed));
+    assert!(test_dir.path.join("wal.log").is_file());
+    assert!(!test_dir.path.join("19.yml").exists());
+    let all = table.get_all().await?;
+    assert_eq!(all.len(), items.len() - 1);
+    assert!(!all.contains_key(removed));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_checkpoint() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let items = example_items();
+    for (hash, item) in items.clone() {
+        table.set(hash, item).await?;
+    }
+
+    // Act
+    let count = table.checkpoint().await?;
+
+    // Assert
+    assert_eq!(count, items.len());
+    assert!(!test_dir.path.join("wal.log").exists());
+    let without_wal = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    assert_eq!(without_wal.get_all().await?, items);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_automatic_checkpoint() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(0);
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+
+    // Act
+    table.set(hash, item.clone()).await?;
+
+    // Assert
+    assert!(!test_dir.path.join("wal.log").exists());
+    let without_wal = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    assert_eq!(without_wal.get(hash).await?, Some(item));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_ignores_partial_record() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+    append_to_log(&test_dir, &[0xff, 0x00, 0x00, 0x00, 0x19]);
+
+    // Act
+    let output = table.get_all().await?;
+
+    // Assert
+    assert_eq!(output.len(), 1);
+    assert_eq!(output.get(&hash), Some(&item));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_appends_after_partial_record() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let mut items = example_items();
+    let (first, first_item) = items.pop_first().expect("should have an item");
+    let (second, second_item) = items.pop_first().expect("should have an item");
+    table.set(first, first_item.clone()).await?;
+    append_to_log(&test_dir, &[0xff, 0x00, 0x00, 0x00, 0x19]);
+
+    // Act
+    table.set(second, second_item.clone()).await?;
+    let output = table.get_all().await?;
+    let count = table.checkpoint().await?;
+
+    // Assert
+    assert_eq!(output.len(), 2);
+    assert_eq!(output.get(&first), Some(&first_item));
+    assert_eq!(output.get(&second), Some(&second_item));
+    assert_eq!(count, 2);
+    let without_wal = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    assert_eq!(without_wal.get(second).await?, Some(second_item));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_ignores_record_with_invalid_checksum() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+    let mut body = hash.as_bytes().to_vec();
+    body.extend_from_slice(b"{}");
+    let length = u32::try_from(body.len()).expect("should fit in u32");
+    let mut record = length.to_le_bytes().to_vec();
+    record.extend_from_slice(&[0x00; 4]);
+    record.extend_from_slice(&body);
+    append_to_log(&test_dir, &record);
+
+    // Act
+    let output = table.get(hash).await?;
+
+    // Assert
+    assert_eq!(output, Some(item));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_set_many_replaces_logged_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let (hash, item) = example_items().pop_first().expect("should have an item");
+    table.set(hash, item.clone()).await?;
+    let replacement = ExampleItem {
+        success: !item.success,
+        ..item
+    };
+
+    // Act
+    table
+        .set_many(BTreeMap::from([(hash, replacement.clone())]), true)
+        .await?;
+    let output = table.get(hash).await?;
+
+    // Assert
+    assert_eq!(output, Some(replacement));
+    assert!(!test_dir.path.join("wal.log").exists());
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_set_skips_reading_chunk() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let mut items = example_items();
+    let (hash, item) = items.pop_first().expect("should have an item");
+    table.set_many(items, true).await?;
+    let count_reads = || {
+        let count = Cell::new(0);
+        logs_assert(|lines| {
+            count.set(
+                lines
+                    .iter()
+                    .filter(|line| line.contains("Reading chunk"))
+                    .count(),
+            );
+            Ok(())
+        });
+        count.get()
+    };
+    let reads_before = count_reads();
+
+    // Act
+    table.set(hash, item.clone()).await?;
+
+    // Assert
+    assert_eq!(count_reads(), reads_before);
+    assert_eq!(table.get(hash).await?, Some(item));
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test]
+async fn wal_appends_after_records_of_another_table() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (test_dir, first) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let second =
+        Table::<20, 1, ExampleItem>::new(test_dir.path.clone()).with_wal(DEFAULT_CHECKPOINT_BYTES);
+    let items = example_items();
+    let mut pending = items.clone();
+
+    // Act
+    for _ in 0..2 {
+        let (hash, item) = pending.pop_first().expect("should have an item");
+        first.set(hash, item).await?;
+        let (hash, item) = pending.pop_first().expect("should have an item");
+        second.set(hash, item).await?;
+    }
+    second.checkpoint().await?;
+    let (hash, item) = pending.pop_first().expect("should have an item");
+    second.set(hash, item).await?;
+    for (hash, item) in pending {
+        first.set(hash, item).await?;
+    }
+    let output = first.get_all().await?;
+    first.checkpoint().await?;
+
+    // Assert
+    assert_eq!(output, items);
+    let without_wal = Table::<20, 1, ExampleItem>::new(test_dir.path.clone());
+    assert_eq!(without_wal.get_all().await?, items);
+    Ok(())
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn wal_get_during_checkpoint_finds_logged_item() -> Result<(), Failure<TableAction>> {
+    // Arrange
+    let (_test_dir, table) = create_table(DEFAULT_CHECKPOINT_BYTES);
+    let items = example_items();
+
+    for round in 0..5 {
+        for (hash, item) in &items {
+            // Arrange
+            let item = ExampleItem {
+                success: round % 2 == 0,
+                ..item.clone()
+            };
+            table.set(*hash, item.clone()).await?;
+            let checkpointing = table.clone();
+
+            // Act
+            let checkpoint = tokio::spawn(async move { checkpointing.checkpoint().await });
+            let mut found = Vec::new();
+            while !checkpoint.is_finished() {
+                found.push(table.get(*hash).await?);
+            }
+            checkpoint.await.expect("checkpoint should not panic")?;
+
+            // Assert
+            assert!(found.iter().all(|found| found.as_ref() == Some(&item)));
+        }
+    }
+    Ok(())
+}
+
+fn append_to_log(test_dir: &TestDirectory, bytes: &[u8]) {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(test_dir.path.join("wal.log"))
+        .expect("should open log");
+    file.write_all(bytes).expect("should write to log");
+}
+
+fn create_table(checkpoint_bytes: u64) -> (TestDirectory, Table<20, 1, ExampleItem>) {
+    let test_dir = TestDirectory::new();
+    let table = Table::new(test_dir.path.clone()).with_wal(checkpoint_bytes);
+    (test_dir, table)
+}
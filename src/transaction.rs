@@ -0,0 +1,318 @@
+use crate::atomic_file::{sync_parent_dir, write_atomic};
+use crate::index::IndexChange;
+use crate::lock_guard::{acquire_lock, try_acquire_lock};
+use crate::table::group_by_chunk;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use tokio::fs::{create_dir_all, read_dir, remove_dir_all, rename};
+use tracing::{debug, info, trace, warn};
+
+const JOURNAL_DIR_NAME: &str = ".journal";
+const COMMIT_MARKER_FILE_NAME: &str = "COMMIT";
+
+/// Counter to make journal names unique within a process.
+static NEXT_JOURNAL: AtomicU64 = AtomicU64::new(0);
+
+/// Staged writes and removals applied to a [`Table`] as a single unit.
+///
+/// Created with [`Table::transaction`].
+pub struct Transaction<'a, const K: usize, const C: usize, T, F> {
+    table: &'a Table<K, C, T, F>,
+    /// Item to write, or `None` to remove, by hash.
+    writes: BTreeMap<Hash<K>, Option<T>>,
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Start a [`Transaction`].
+    ///
+    /// Nothing is written until [`Transaction::commit`] is called.
+    #[must_use]
+    pub fn transaction(&self) -> Transaction<'_, K, C, T, F> {
+        Transaction {
+            table: self,
+            writes: BTreeMap::new(),
+        }
+    }
+
+    /// Get the directory containing the journals of in-progress transactions.
+    fn get_journal_root(&self) -> PathBuf {
+        self.directory.join(JOURNAL_DIR_NAME)
+    }
+
+    /// Complete or discard transactions interrupted by a crash.
+    ///
+    /// - Journals with a commit marker are rolled forward
+    /// - Journals without a commit marker are discarded so no chunk is changed
+    /// - Journals locked by a transaction still in progress are skipped
+    ///
+    /// Called by [`Table::init`].
+    ///
+    /// Returns the number of transactions rolled forward
+    pub async fn recover(&self) -> Result<usize, Failure<TableAction>> {
+        let root = self.get_journal_root();
+        if !root.is_dir() {
+            return Ok(0);
+        }
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::Recover))?;
+        let mut journals = Vec::new();
+        let mut dir = read_dir(&root)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadDir, &root))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            let path = entry.path();
+            if path.is_dir() {
+                journals.push(path);
+            }
+        }
+        journals.sort();
+        let mut recovered = 0;
+        for journal in journals {
+            let Some(_journal_lock) = try_acquire_lock(&journal, &self.lock_options)
+                .await
+                .map_err(Failure::wrap(TableAction::Recover))?
+            else {
+                debug!(path = %journal.display(), "Skipping journal of transaction in progress");
+                continue;
+            };
+            if !journal.is_dir() {
+                trace!(path = %journal.display(), "Journal completed before it was locked");
+                continue;
+            }
+            if journal.join(COMMIT_MARKER_FILE_NAME).is_file() {
+                let mut locks = Vec::new();
+                for chunk_hash in self.list_journal_chunks(&journal).await? {
+                    let lock = self
+                        .lock_chunk(chunk_hash)
+                        .await
+                        .map_err(Failure::wrap(TableAction::Recover))?;
+                    locks.push(lock);
+                }
+                warn!(path = %journal.display(), "Rolling forward interrupted transaction");
+                self.roll_forward(&journal).await?;
+                recovered += 1;
+            } else {
+                warn!(path = %journal.display(), "Discarding uncommitted transaction");
+                remove_dir_all(&journal)
+                    .await
+                    .map_err(Failure::wrap_with_path(TableAction::Recover, &journal))?;
+            }
+        }
+        Ok(recovered)
+    }
+
+    /// Get the hashes of the chunks staged in a journal.
+    async fn list_journal_chunks(
+        &self,
+        journal: &Path,
+    ) -> Result<BTreeSet<Hash<C>>, Failure<TableAction>> {
+        let mut chunks = BTreeSet::new();
+        let mut dir = read_dir(journal)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadDir, journal))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            if let Some(chunk_hash) = self.parse_chunk_path(&entry.path()) {
+                chunks.insert(chunk_hash);
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Move every chunk staged in a committed journal into the table then remove the
+    /// journal.
+    ///
+    /// Must be called while holding the locks of the staged chunks.
+    ///
+    /// Safe to repeat if interrupted.
+    async fn roll_forward(&self, journal: &Path) -> Result<usize, Failure<TableAction>> {
+        let mut count = 0;
+        let mut dir = read_dir(journal)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadDir, journal))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            let staged = entry.path();
+            let Some(chunk_hash) = self.parse_chunk_path(&staged) else {
+                continue;
+            };
+            let path = self
+                .directory
+                .join(staged.file_name().expect("staged chunk should have a name"));
+            trace!(from = %staged.display(), to = %path.display(), "Moving staged chunk");
+            rename(&staged, &path)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::WriteChunk, &path))?;
+            sync_parent_dir(&path)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::WriteChunk, &path))?;
+            self.complete_chunk_write(chunk_hash, &path).await?;
+            count += 1;
+        }
+        remove_dir_all(journal)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::RemoveChunk, journal))?;
+        Ok(count)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Transaction<'_, K, C, T, F> {
+    /// Stage an item to be added or replaced.
+    pub fn set(&mut self, hash: Hash<K>, item: T) -> &mut Self {
+        self.writes.insert(hash, Some(item));
+        self
+    }
+
+    /// Stage an item to be removed.
+    pub fn remove(&mut self, hash: Hash<K>) -> &mut Self {
+        self.writes.insert(hash, None);
+        self
+    }
+
+    /// Number of staged writes and removals.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Whether nothing is staged.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Transaction<'_, K, C, T, F>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Apply every staged write and removal.
+    ///
+    /// - Chunk locks are acquired in hash order so transactions never deadlock
+    /// - Updated chunks are staged in a journal which is marked as committed once every
+    ///   chunk is written
+    /// - Staged chunks are then renamed into the table
+    ///
+    /// If the process stops before the journal is committed no chunk is changed,
+    /// otherwise the transaction is completed by [`Table::recover`].
+    ///
+    /// The write-ahead log, if enabled, is checkpointed first.
+    ///
+    /// Secondary indexes are updated after the chunks so may need to be rebuilt with
+    /// [`Table::rebuild_index`] after a crash.
+    ///
+    /// Returns the number of chunks written
+    pub async fn commit(self) -> Result<usize, Failure<TableAction>> {
+        let table = self.table;
+        if self.writes.is_empty() {
+            return Ok(0);
+        }
+        let _writer = table
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::Commit))?;
+        let (_, _wal) = table
+            .checkpoint_and_lock_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::Commit))?;
+        let chunks = group_by_chunk::<K, C, Option<T>>(self.writes);
+        let mut locks = Vec::with_capacity(chunks.len());
+        for chunk_hash in chunks.keys() {
+            let lock = table
+                .lock_chunk(*chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Commit))?;
+            locks.push(lock);
+        }
+        let root = table.get_journal_root();
+        create_dir_all(&root)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::Commit, &root))?;
+        let journal = root.join(get_journal_name());
+        let _journal_lock = acquire_lock(&journal, &table.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::Commit))?;
+        debug!(path = %journal.display(), chunks = chunks.len(), "Committing transaction");
+        let index_changes = match Self::stage(table, chunks, &journal).await {
+            Ok(changes) => changes,
+            Err(failure) => {
+                let _ = remove_dir_all(&journal).await;
+                return Err(failure);
+            }
+        };
+        let count = table.roll_forward(&journal).await?;
+        table
+            .apply_index_changes(index_changes)
+            .await
+            .map_err(Failure::wrap(TableAction::Commit))?;
+        drop(locks);
+        info!(chunks = count, "Committed transaction");
+        Ok(count)
+    }
+
+    /// Write every updated chunk to the journal then mark it as committed.
+    ///
+    /// Returns the changes to apply to the secondary indexes
+    async fn stage(
+        table: &Table<K, C, T, F>,
+        chunks: BTreeMap<Hash<C>, BTreeMap<Hash<K>, Option<T>>>,
+        journal: &Path,
+    ) -> Result<Vec<IndexChange<K>>, Failure<TableAction>> {
+        create_dir_all(journal)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::Commit, journal))?;
+        let mut index_changes = Vec::new();
+        for (chunk_hash, writes) in chunks {
+            let mut chunk = table
+                .load_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Commit))?;
+            for (hash, item) in writes {
+                let old = chunk.remove(&hash);
+                index_changes.extend(table.indexes.changes(hash, old.as_ref(), item.as_ref()));
+                if let Some(item) = item {
+                    chunk.insert(hash, item);
+                }
+            }
+            let (path, bytes) = table.encode_chunk(chunk_hash, &chunk)?;
+            let staged = journal.join(path.file_name().expect("chunk should have a name"));
+            write_atomic(&staged, bytes)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::Commit, &staged))?;
+        }
+        let marker = journal.join(COMMIT_MARKER_FILE_NAME);
+        write_atomic(&marker, [])
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::Commit, &marker))?;
+        Ok(index_changes)
+    }
+}
+
+/// Get a unique name for a journal.
+fn get_journal_name() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Duration should be valid")
+        .as_nanos();
+    let counter = NEXT_JOURNAL.fetch_add(1, Ordering::Relaxed);
+    format!("{timestamp}-{}-{counter}", process::id())
+}
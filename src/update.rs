@@ -0,0 +1,153 @@
+use crate::table::get_chunk_hash;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use futures::stream::{self, StreamExt};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tokio::task;
+use tracing::trace;
+
+/// Previous and new item returned by [`Table::update`].
+pub type Update<T> = (Option<T>, Option<T>);
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Read, modify and write an item while holding the chunk lock.
+    ///
+    /// - `update` receives the current item, or `None` if it does not exist
+    /// - Returning `None` removes the item
+    ///
+    /// Returns the previous and new item
+    pub async fn update(
+        &self,
+        hash: Hash<K>,
+        update: impl FnOnce(Option<T>) -> Option<T>,
+    ) -> Result<Update<T>, Failure<TableAction>> {
+        let result = self
+            .modify(hash, |item| Ok(update(item)))
+            .await
+            .map_err(Failure::wrap(TableAction::Update))?;
+        trace!(
+            hash = %hash,
+            existed = result.0.is_some(),
+            exists = result.1.is_some(),
+            "Update item"
+        );
+        Ok(result)
+    }
+
+    /// Read, modify and write many items.
+    ///
+    /// Each chunk is locked while its items are updated and written once. At most
+    /// [`Table::with_concurrency`] chunks are updated at once so `update` may be called
+    /// from several tasks.
+    ///
+    /// See [`Table::update`].
+    ///
+    /// Returns the previous and new item by hash
+    pub async fn update_many(
+        &self,
+        hashes: impl IntoIterator<Item = Hash<K>>,
+        update: impl Fn(Hash<K>, Option<T>) -> Option<T> + Send + Sync + 'static,
+    ) -> Result<BTreeMap<Hash<K>, Update<T>>, Failure<TableAction>> {
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateMany))?;
+        let (_, _wal) = self
+            .checkpoint_and_lock_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateMany))?;
+        let mut chunks: BTreeMap<Hash<C>, BTreeSet<Hash<K>>> = BTreeMap::new();
+        for hash in hashes {
+            chunks.entry(get_chunk_hash(hash)).or_default().insert(hash);
+        }
+        let chunk_count = chunks.len();
+        trace!(chunks = chunk_count, "Update many items");
+        let update = Arc::new(update);
+        let results: Vec<_> = stream::iter(chunks)
+            .map(|(chunk_hash, hashes)| {
+                let table = self.clone();
+                let update = update.clone();
+                task::spawn(async move {
+                    table
+                        .update_chunk_items(chunk_hash, hashes, update.as_ref())
+                        .await
+                })
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        let mut updates = BTreeMap::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(Ok(chunk_updates)) => updates.extend(chunk_updates),
+                Ok(Err(e)) => errors.push(e),
+                Err(source) => errors.push(Failure::new(TableAction::JoinTask, source)),
+            }
+        }
+        if errors.is_empty() {
+            trace!(count = updates.len(), "Update many items complete");
+            Ok(updates)
+        } else {
+            let succeeded = chunk_count - errors.len();
+            let failed = errors.len();
+            trace!(succeeded, failed, "Update many items complete");
+            let mut failure = Failure::from_action(TableAction::UpdateMany)
+                .with("succeeded", succeeded.to_string())
+                .with("failed", failed.to_string());
+            for error in errors {
+                failure = failure.with_related(error);
+            }
+            Err(failure)
+        }
+    }
+
+    /// Read, modify and write items of a chunk while holding the chunk lock.
+    ///
+    /// Nothing is written if none of the items exist before or after the update.
+    ///
+    /// Returns the previous and new item by hash
+    async fn update_chunk_items(
+        &self,
+        chunk_hash: Hash<C>,
+        hashes: BTreeSet<Hash<K>>,
+        update: &impl Fn(Hash<K>, Option<T>) -> Option<T>,
+    ) -> Result<BTreeMap<Hash<K>, Update<T>>, Failure<TableAction>> {
+        let _lock = self
+            .lock_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut chunk = self
+            .load_chunk(chunk_hash)
+            .await
+            .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        let mut updates = BTreeMap::new();
+        let mut changes = Vec::new();
+        let mut is_modified = false;
+        for hash in hashes {
+            let old = chunk.remove(&hash);
+            let new = update(hash, old.clone());
+            changes.extend(self.indexes.changes(hash, old.as_ref(), new.as_ref()));
+            is_modified |= old.is_some() || new.is_some();
+            if let Some(item) = &new {
+                chunk.insert(hash, item.clone());
+            }
+            updates.insert(hash, (old, new));
+        }
+        if is_modified {
+            self.write_chunk(chunk_hash, chunk)
+                .await
+                .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+            self.apply_index_changes(changes)
+                .await
+                .map_err(Failure::wrap(TableAction::UpdateChunk))?;
+        }
+        Ok(updates)
+    }
+}
@@ -0,0 +1,342 @@
+use crate::atomic_file::TEMP_FILE_EXTENSION;
+use crate::lenient::RawKey;
+use crate::lock_guard::{find_files, find_lock_files};
+use crate::table::get_chunk_hash;
+use crate::{ChunkFormat, Compression, Hash, ReadDiagnostic, Table, TableAction};
+use rogue_logging::Failure;
+use serde::de::{DeserializeOwned, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fmt::{self, Formatter};
+use std::path::{Path, PathBuf};
+use tokio::fs::{read, read_dir};
+use tracing::{debug, info, warn};
+
+/// Item in a chunk that its key does not truncate to.
+///
+/// [`Table::get`] can never find these items.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MisplacedItem<const K: usize> {
+    /// Key of the item.
+    pub key: Hash<K>,
+    /// Path of the chunk file the item is in.
+    pub path: PathBuf,
+    /// Path of the chunk file the item belongs in.
+    pub expected_path: PathBuf,
+}
+
+/// Key with more than one entry in a chunk file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicateKey {
+    /// Path of the chunk file.
+    pub path: PathBuf,
+    /// Key as written in the chunk file.
+    pub key: String,
+    /// Number of entries with the key.
+    pub count: usize,
+}
+
+/// Problems found by [`Table::verify`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerifyReport<const K: usize> {
+    /// Number of chunk files checked.
+    pub chunks: usize,
+    /// Items in a chunk their key does not truncate to.
+    pub misplaced: Vec<MisplacedItem<K>>,
+    /// Files named like chunk files of the table but without a valid `Hash<C>`.
+    pub invalid_chunk_names: Vec<PathBuf>,
+    /// Chunks and items that can't be deserialized, including keys that are not a
+    /// valid `Hash<K>`.
+    pub unreadable: Vec<ReadDiagnostic>,
+    /// Keys with more than one entry in a chunk, only one of which is read.
+    pub duplicate_keys: Vec<DuplicateKey>,
+    /// Lock files in the table directory.
+    pub locks: Vec<PathBuf>,
+    /// Temporary files left by interrupted writes.
+    pub temp_files: Vec<PathBuf>,
+}
+
+impl<const K: usize> VerifyReport<K> {
+    /// Whether no problems were found.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.misplaced.is_empty()
+            && self.invalid_chunk_names.is_empty()
+            && self.unreadable.is_empty()
+            && self.duplicate_keys.is_empty()
+            && self.locks.is_empty()
+            && self.temp_files.is_empty()
+    }
+}
+
+/// Number of entries of each key in a chunk map.
+///
+/// Keys are counted as the map is visited since collecting it into a map would
+/// keep only one entry per key.
+#[derive(Default)]
+struct KeyCounts(BTreeMap<RawKey, usize>);
+
+impl<'de> Deserialize<'de> for KeyCounts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(KeyCountsVisitor)
+    }
+}
+
+/// Visitor counting the keys of a map without deserializing its values.
+struct KeyCountsVisitor;
+
+impl<'de> Visitor<'de> for KeyCountsVisitor {
+    type Value = KeyCounts;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "a map of items")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut counts = KeyCounts::default();
+        while let Some(key) = map.next_key::<RawKey>()? {
+            map.next_value::<IgnoredAny>()?;
+            *counts.0.entry(key).or_default() += 1;
+        }
+        Ok(counts)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Check the integrity of every chunk file.
+    ///
+    /// Nothing is modified. Lock and temporary files of writes in progress are
+    /// reported too.
+    ///
+    /// [`Table::repair`] moves misplaced items and [`Table::clear_stale_locks`] removes
+    /// locks left by crashed processes.
+    pub async fn verify(&self) -> Result<VerifyReport<K>, Failure<TableAction>> {
+        let mut report = VerifyReport::default();
+        for path in self.list_files().await? {
+            match self.parse_chunk_path(&path) {
+                Some(chunk_hash) => self.verify_chunk(chunk_hash, &path, &mut report).await?,
+                None if self.is_invalid_chunk_name(&path) => {
+                    report.invalid_chunk_names.push(path);
+                }
+                None => {}
+            }
+        }
+        report.locks = find_lock_files(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(
+                TableAction::Verify,
+                &self.directory,
+            ))?;
+        report.locks.sort();
+        report.temp_files = find_files(&self.directory, TEMP_FILE_EXTENSION)
+            .await
+            .map_err(Failure::wrap_with_path(
+                TableAction::Verify,
+                &self.directory,
+            ))?;
+        report.temp_files.sort();
+        debug!(
+            chunks = report.chunks,
+            misplaced = report.misplaced.len(),
+            invalid_chunk_names = report.invalid_chunk_names.len(),
+            unreadable = report.unreadable.len(),
+            duplicate_keys = report.duplicate_keys.len(),
+            locks = report.locks.len(),
+            temp_files = report.temp_files.len(),
+            "Verified table"
+        );
+        Ok(report)
+    }
+
+    /// Move items that are in the wrong chunk into the chunk their key truncates to.
+    ///
+    /// - Acquires the table-wide lock so no other writer runs while items are moved
+    /// - Chunks that can't be read are skipped
+    /// - An item is left in place if the chunk it belongs in already has the key
+    ///
+    /// Returns the number of items moved
+    pub async fn repair(&self) -> Result<usize, Failure<TableAction>> {
+        let exclusive = self
+            .exclusive()
+            .await
+            .map_err(Failure::wrap(TableAction::Repair))?;
+        exclusive
+            .checkpoint_wal()
+            .await
+            .map_err(Failure::wrap(TableAction::Repair))?;
+        let report = exclusive
+            .verify()
+            .await
+            .map_err(Failure::wrap(TableAction::Repair))?;
+        let mut misplaced: BTreeMap<PathBuf, Vec<Hash<K>>> = BTreeMap::new();
+        for item in report.misplaced {
+            misplaced.entry(item.path).or_default().push(item.key);
+        }
+        let mut moved = 0;
+        for (path, keys) in misplaced {
+            moved += exclusive
+                .move_misplaced(&path, keys)
+                .await
+                .map_err(Failure::wrap(TableAction::Repair))?;
+        }
+        info!(path = %self.directory.display(), moved, "Repaired table");
+        Ok(moved)
+    }
+
+    /// Get the path of every file in the table directory, sorted.
+    async fn list_files(&self) -> Result<Vec<PathBuf>, Failure<TableAction>> {
+        let mut files = Vec::new();
+        let mut dir = read_dir(&self.directory)
+            .await
+            .map_err(Failure::wrap_with_path(
+                TableAction::ReadDir,
+                &self.directory,
+            ))?;
+        while let Some(entry) = dir
+            .next_entry()
+            .await
+            .map_err(Failure::wrap(TableAction::ReadEntry))?
+        {
+            let path = entry.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Whether a file has the extension of a chunk file but not a valid name.
+    fn is_invalid_chunk_name(&self, path: &Path) -> bool {
+        let stripped = Compression::from_path(path).strip_from_path(path);
+        stripped
+            .extension()
+            .is_some_and(|extension| extension == self.format.extension())
+            && path != self.get_manifest_path()
+    }
+
+    /// Check the items of a chunk file.
+    async fn verify_chunk(
+        &self,
+        chunk_hash: Hash<C>,
+        path: &Path,
+        report: &mut VerifyReport<K>,
+    ) -> Result<(), Failure<TableAction>> {
+        report.chunks += 1;
+        let chunk = {
+            let _lock = self.lock_chunk_shared(chunk_hash).await?;
+            self.read_chunk_lenient(path).await?
+        };
+        report.unreadable.extend(chunk.diagnostics);
+        for key in chunk.items.into_keys() {
+            let expected = get_chunk_hash(key);
+            if expected != chunk_hash {
+                report.misplaced.push(MisplacedItem {
+                    key,
+                    path: path.to_path_buf(),
+                    expected_path: self.get_chunk_path(expected),
+                });
+            }
+        }
+        if self.format.is_self_describing() {
+            report
+                .duplicate_keys
+                .extend(self.find_duplicate_keys(path).await?);
+        }
+        Ok(())
+    }
+
+    /// Find keys with more than one entry in a chunk file.
+    ///
+    /// Chunks that can't be deserialized are reported by
+    /// [`Table::read_chunk_lenient`] instead.
+    async fn find_duplicate_keys(
+        &self,
+        path: &Path,
+    ) -> Result<Vec<DuplicateKey>, Failure<TableAction>> {
+        let bytes = read(path)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::ReadChunk, path))?;
+        let Ok(bytes) = Compression::from_path(path).decompress(bytes) else {
+            return Ok(Vec::new());
+        };
+        let Ok(maps) = self.format.deserialize_maps::<KeyCounts>(&bytes) else {
+            return Ok(Vec::new());
+        };
+        let mut counts: BTreeMap<RawKey, usize> = BTreeMap::new();
+        for map in maps {
+            for (key, count) in map.0 {
+                *counts.entry(key).or_default() += count;
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(key, count)| DuplicateKey {
+                path: path.to_path_buf(),
+                key: key.to_string(),
+                count,
+            })
+            .collect())
+    }
+
+    /// Move misplaced items out of a chunk file.
+    ///
+    /// Items are written to the chunk they belong in before they are removed from
+    /// this one so a crash leaves a harmless copy rather than losing them.
+    ///
+    /// Returns the number of items moved
+    async fn move_misplaced(
+        &self,
+        path: &Path,
+        keys: Vec<Hash<K>>,
+    ) -> Result<usize, Failure<TableAction>> {
+        let source_hash = self
+            .parse_chunk_path(path)
+            .expect("misplaced items should be in a chunk file");
+        let _lock = self.lock_chunk(source_hash).await?;
+        let mut source = match self.read_chunk(path).await {
+            Ok(source) => source,
+            Err(failure) => {
+                warn!(path = %path.display(), %failure, "Skipping unreadable chunk");
+                return Ok(0);
+            }
+        };
+        let mut targets: BTreeMap<Hash<C>, BTreeMap<Hash<K>, T>> = BTreeMap::new();
+        for key in keys {
+            if let Some(item) = source.remove(&key) {
+                targets
+                    .entry(get_chunk_hash(key))
+                    .or_default()
+                    .insert(key, item);
+            }
+        }
+        let mut moved = 0;
+        for (target_hash, items) in targets {
+            let _target_lock = self.lock_chunk(target_hash).await?;
+            let mut target = self.load_chunk(target_hash).await?;
+            for (key, item) in items {
+                match target.entry(key) {
+                    Entry::Occupied(_) => {
+                        warn!(key = %key, path = %path.display(), "Leaving misplaced item as its chunk already has the key");
+                        source.insert(key, item);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(item);
+                        moved += 1;
+                    }
+                }
+            }
+            self.write_chunk(target_hash, target).await?;
+        }
+        self.write_chunk(source_hash, source).await?;
+        Ok(moved)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::hash::fnv1a;
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use thiserror::Error as ThisError;
+use tracing::trace;
+
+/// Version of an item derived from its serialized content.
+///
+/// Any change to an item changes its version so it can be used to detect
+/// intervening writes.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ItemVersion(u64);
+
+impl Debug for ItemVersion {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{:016x}", self.0)
+    }
+}
+
+impl Display for ItemVersion {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{:016x}", self.0)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Serialize,
+{
+    /// Get the version of an item.
+    fn get_version(&self, hash: Hash<K>, item: &T) -> Result<ItemVersion, Failure<TableAction>> {
+        let entry = BTreeMap::from([(hash, item)]);
+        let bytes = self
+            .format
+            .serialize(&entry)
+            .map_err(Failure::wrap(TableAction::Serialize))?;
+        Ok(ItemVersion(fnv1a(&bytes)))
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+    /// Get an item and its version.
+    ///
+    /// Returns `None` if the item is not found.
+    pub async fn get_versioned(
+        &self,
+        hash: Hash<K>,
+    ) -> Result<Option<(T, ItemVersion)>, Failure<TableAction>> {
+        let Some(item) = self.get(hash).await? else {
+            return Ok(None);
+        };
+        let version = self.get_version(hash, &item)?;
+        Ok(Some((item, version)))
+    }
+
+    /// Add or replace an item only if it has not changed since it was read.
+    ///
+    /// - `expected` is the version from [`Table::get_versioned`], or `None` if the item
+    ///   must not exist
+    /// - Fails with [`ConflictError`] if the current version is different
+    ///
+    /// Returns the version of the new item
+    pub async fn compare_and_set(
+        &self,
+        hash: Hash<K>,
+        expected: Option<ItemVersion>,
+        item: T,
+    ) -> Result<ItemVersion, Failure<TableAction>> {
+        let version = self.get_version(hash, &item)?;
+        self.modify(hash, |old| {
+            let actual = old.map(|old| self.get_version(hash, &old)).transpose()?;
+            if actual != expected {
+                return Err(Failure::new(
+                    TableAction::CompareAndSet,
+                    ConflictError::Changed { expected, actual },
+                )
+                .with("hash", hash.to_hex()));
+            }
+            Ok(Some(item))
+        })
+        .await?;
+        trace!(hash = %hash, %version, "Compare and set item");
+        Ok(version)
+    }
+}
+
+/// Errors when an item changed since it was read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ThisError)]
+pub enum ConflictError {
+    #[error("Item was changed by another writer\nExpected: {expected:?}\nActual: {actual:?}")]
+    Changed {
+        expected: Option<ItemVersion>,
+        actual: Option<ItemVersion>,
+    },
+}
@@ -0,0 +1,504 @@
+use crate::lock_guard::{LockGuard, acquire_lock};
+use crate::table::{get_chunk_hash, group_by_chunk};
+use crate::{ChunkFormat, Hash, Table, TableAction};
+use rogue_logging::Failure;
+use serde::Serialize;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::PoisonError;
+use tokio::fs::{File, OpenOptions, read, remove_file};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{debug, info, trace, warn};
+
+const WAL_FILE_NAME: &str = "wal.log";
+const FRAME_LENGTH_BYTES: usize = 4;
+const FRAME_CHECKSUM_BYTES: usize = 4;
+
+/// Default size of the write-ahead log that triggers an automatic checkpoint.
+pub const DEFAULT_CHECKPOINT_BYTES: u64 = 1024 * 1024;
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F> {
+    /// Append [`Table::set`] and [`Table::remove`] to a write-ahead log instead of
+    /// rewriting the chunk.
+    ///
+    /// - Reads merge the log with the chunk contents
+    /// - [`Table::set`] appends without reading the chunk unless a secondary index
+    ///   needs the previous item
+    /// - [`Table::checkpoint`] folds the log into the chunk files
+    /// - The log is checkpointed automatically once it exceeds `checkpoint_bytes`
+    /// - [`Table::set_many`], [`Table::remove_many`], transactions and resharding
+    ///   checkpoint the log first and block appends until they finish
+    ///
+    /// Every writer of the table must use the write-ahead log.
+    ///
+    /// [`DEFAULT_CHECKPOINT_BYTES`] suits most tables.
+    #[must_use]
+    pub fn with_wal(mut self, checkpoint_bytes: u64) -> Self {
+        self.wal_checkpoint_bytes = Some(checkpoint_bytes);
+        self
+    }
+
+    /// Whether writes are appended to the write-ahead log.
+    pub(crate) fn is_wal_enabled(&self) -> bool {
+        self.wal_checkpoint_bytes.is_some()
+    }
+
+    /// Get the path to the write-ahead log.
+    pub(crate) fn get_wal_path(&self) -> PathBuf {
+        self.directory.join(WAL_FILE_NAME)
+    }
+
+    /// Read the hash and serialized entry of every record in the write-ahead log.
+    ///
+    /// - A partial or corrupt record at the end of the log, left by a crash while
+    ///   appending, is ignored
+    /// - Returns no records if the log does not exist, including if a checkpoint
+    ///   removed it
+    async fn read_wal_records(&self) -> Result<Vec<(Hash<K>, Vec<u8>)>, Failure<TableAction>> {
+        if !self.is_wal_enabled() {
+            return Ok(Vec::new());
+        }
+        let path = self.get_wal_path();
+        let bytes = match read(&path).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(Failure::new(TableAction::ReadLog, error).with_path(&path)),
+        };
+        let scan = parse_wal_records(&bytes);
+        if scan.valid < bytes.len() {
+            warn!(path = %path.display(), "Ignoring partial write-ahead log record");
+        }
+        Ok(scan.records)
+    }
+
+    /// Get the hashes of chunks with records in the write-ahead log.
+    pub(crate) async fn list_wal_chunks(&self) -> Result<BTreeSet<Hash<C>>, Failure<TableAction>> {
+        let records = self.read_wal_records().await?;
+        Ok(records
+            .into_iter()
+            .map(|(hash, _)| get_chunk_hash(hash))
+            .collect())
+    }
+
+    /// Read whether the latest write-ahead log entry of each item in a chunk sets or
+    /// removes it.
+    ///
+    /// Items are skipped without being deserialized. See [`Table::read_wal_chunk`].
+    #[expect(
+        clippy::zero_sized_map_values,
+        reason = "chunk formats only deserialize maps"
+    )]
+    pub(crate) async fn read_wal_keys(
+        &self,
+        chunk_hash: Hash<C>,
+    ) -> Result<BTreeMap<Hash<K>, bool>, Failure<TableAction>> {
+        let mut keys = BTreeMap::new();
+        for (hash, bytes) in self.read_wal_records().await? {
+            if get_chunk_hash::<K, C>(hash) != chunk_hash {
+                continue;
+            }
+            let entry: BTreeMap<Hash<K>, IgnoredAny> =
+                self.format
+                    .deserialize(&bytes)
+                    .map_err(Failure::wrap_with_path(
+                        TableAction::ReadLog,
+                        self.get_wal_path(),
+                    ))?;
+            keys.insert(hash, entry.contains_key(&hash));
+        }
+        Ok(keys)
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: DeserializeOwned,
+{
+    /// Read the latest write-ahead log entry of each item.
+    ///
+    /// - `None` entries are removals
+    /// - If `chunk_hash` is set only entries in that chunk are read
+    async fn read_wal_entries(
+        &self,
+        chunk_hash: Option<Hash<C>>,
+    ) -> Result<BTreeMap<Hash<K>, Option<T>>, Failure<TableAction>> {
+        let mut entries = BTreeMap::new();
+        for (hash, bytes) in self.read_wal_records().await? {
+            if chunk_hash.is_some_and(|chunk_hash| get_chunk_hash::<K, C>(hash) != chunk_hash) {
+                continue;
+            }
+            let mut entry: BTreeMap<Hash<K>, T> =
+                self.format
+                    .deserialize(&bytes)
+                    .map_err(Failure::wrap_with_path(
+                        TableAction::ReadLog,
+                        self.get_wal_path(),
+                    ))?;
+            entries.insert(hash, entry.remove(&hash));
+        }
+        Ok(entries)
+    }
+
+    /// Read the write-ahead log entries of a chunk to merge with [`merge_wal`].
+    ///
+    /// Must be read before the chunk file. A checkpoint writes the chunk files before
+    /// removing the log so a chunk file read afterwards includes any entry already
+    /// gone from the log.
+    pub(crate) async fn read_wal_chunk(
+        &self,
+        chunk_hash: Hash<C>,
+    ) -> Result<BTreeMap<Hash<K>, Option<T>>, Failure<TableAction>> {
+        if !self.is_wal_enabled() {
+            return Ok(BTreeMap::new());
+        }
+        self.read_wal_entries(Some(chunk_hash)).await
+    }
+}
+
+/// Apply write-ahead log entries read by [`Table::read_wal_chunk`] to a chunk.
+pub(crate) fn merge_wal<const K: usize, T>(
+    chunk: &mut BTreeMap<Hash<K>, T>,
+    entries: BTreeMap<Hash<K>, Option<T>>,
+) {
+    for (hash, entry) in entries {
+        match entry {
+            Some(item) => chunk.insert(hash, item),
+            None => chunk.remove(&hash),
+        };
+    }
+}
+
+impl<const K: usize, const C: usize, T, F: ChunkFormat> Table<K, C, T, F>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Append the result of `modify` to the write-ahead log while holding the log lock.
+    ///
+    /// The lock is held until the secondary indexes are updated so they are changed
+    /// in the same order as the log.
+    ///
+    /// See [`Table::modify`].
+    ///
+    /// Returns the previous and new item
+    pub(crate) async fn append_wal<M>(
+        &self,
+        hash: Hash<K>,
+        modify: M,
+    ) -> Result<(Option<T>, Option<T>), Failure<TableAction>>
+    where
+        M: FnOnce(Option<T>) -> Result<Option<T>, Failure<TableAction>>,
+    {
+        let lock = acquire_lock(self.get_wal_path(), &self.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::AppendLog))?;
+        let old = self.get(hash).await?;
+        let new = modify(old.clone())?;
+        if old.is_none() && new.is_none() {
+            return Ok((old, new));
+        }
+        let changes = self.indexes.changes(hash, old.as_ref(), new.as_ref());
+        let size = self.write_wal_record(hash, new.as_ref()).await?;
+        self.apply_index_changes(changes)
+            .await
+            .map_err(Failure::wrap(TableAction::AppendLog))?;
+        self.fold_wal_if_full(size).await?;
+        drop(lock);
+        Ok((old, new))
+    }
+
+    /// Append an item, or `None` to remove it, to the write-ahead log without reading
+    /// the previous item.
+    ///
+    /// Must only be used if no secondary index needs the previous item.
+    pub(crate) async fn replace_wal(
+        &self,
+        hash: Hash<K>,
+        item: Option<T>,
+    ) -> Result<(), Failure<TableAction>> {
+        let _lock = acquire_lock(self.get_wal_path(), &self.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::AppendLog))?;
+        let size = self.write_wal_record(hash, item.as_ref()).await?;
+        self.fold_wal_if_full(size).await
+    }
+
+    /// Append a record to the write-ahead log while holding the log lock.
+    ///
+    /// Returns the size of the log
+    async fn write_wal_record(
+        &self,
+        hash: Hash<K>,
+        item: Option<&T>,
+    ) -> Result<u64, Failure<TableAction>> {
+        let path = self.get_wal_path();
+        let entry: BTreeMap<Hash<K>, &T> = item.into_iter().map(|item| (hash, item)).collect();
+        let entry = self
+            .format
+            .serialize(&entry)
+            .map_err(Failure::wrap_with_path(TableAction::Serialize, &path))?;
+        let tail = self
+            .append_wal_record(&path, hash, &entry)
+            .await
+            .map_err(Failure::wrap_with_path(TableAction::AppendLog, &path))?;
+        *self.wal_tail.lock().unwrap_or_else(PoisonError::into_inner) = Some(tail);
+        trace!(hash = %hash, size = tail.end, "Appended to write-ahead log");
+        Ok(tail.end)
+    }
+
+    /// Append a record after the last valid record of the write-ahead log.
+    ///
+    /// A partial record left by a crash while appending is truncated first.
+    ///
+    /// Returns the appended record
+    async fn append_wal_record(
+        &self,
+        path: &Path,
+        hash: Hash<K>,
+        entry: &[u8],
+    ) -> io::Result<WalTail> {
+        let (record, checksum) = create_wal_record(hash, entry);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)
+            .await?;
+        let len = file.metadata().await?.len();
+        let end = self
+            .find_wal_tail(&mut file, len)
+            .await?
+            .map_or(0, |tail| tail.end);
+        if end < len {
+            warn!(path = %path.display(), "Truncating partial write-ahead log record");
+            file.set_len(end).await?;
+        }
+        file.seek(SeekFrom::Start(end)).await?;
+        file.write_all(&record).await?;
+        file.sync_data().await?;
+        Ok(WalTail {
+            start: end,
+            end: end + to_offset(record.len()),
+            checksum,
+        })
+    }
+
+    /// Find the last valid record of the write-ahead log.
+    ///
+    /// If the log still has the record last appended by this table then only the
+    /// records appended after it, by another process, are read. Otherwise the whole
+    /// log is read.
+    ///
+    /// Returns `None` if the log has no valid record
+    async fn find_wal_tail(&self, file: &mut File, len: u64) -> io::Result<Option<WalTail>> {
+        let cached = *self.wal_tail.lock().unwrap_or_else(PoisonError::into_inner);
+        let tail = match cached {
+            Some(tail) if has_wal_record::<K>(file, tail, len).await? => Some(tail),
+            _ => None,
+        };
+        let offset = tail.map_or(0, |tail| tail.end);
+        if offset == len {
+            return Ok(tail);
+        }
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).await?;
+        let scan = parse_wal_records::<K>(&bytes);
+        Ok(match scan.last {
+            Some((start, checksum)) => Some(WalTail {
+                start: offset + to_offset(start),
+                end: offset + to_offset(scan.valid),
+                checksum,
+            }),
+            None => tail,
+        })
+    }
+
+    /// Fold the write-ahead log into the chunk files if it exceeds the checkpoint size.
+    async fn fold_wal_if_full(&self, size: u64) -> Result<(), Failure<TableAction>> {
+        if self
+            .wal_checkpoint_bytes
+            .is_some_and(|checkpoint_bytes| size > checkpoint_bytes)
+        {
+            self.fold_wal().await?;
+        }
+        Ok(())
+    }
+
+    /// Fold the write-ahead log into the chunk files then remove it.
+    ///
+    /// Chunk files are written in the normal format so they can still be read without
+    /// the log.
+    ///
+    /// Returns the number of log entries applied
+    pub async fn checkpoint(&self) -> Result<usize, Failure<TableAction>> {
+        let _writer = self
+            .lock_writer()
+            .await
+            .map_err(Failure::wrap(TableAction::Checkpoint))?;
+        self.checkpoint_wal().await
+    }
+
+    /// Fold the write-ahead log into the chunk files without registering as a writer.
+    ///
+    /// See [`Table::checkpoint`].
+    pub(crate) async fn checkpoint_wal(&self) -> Result<usize, Failure<TableAction>> {
+        let (count, _lock) = self.checkpoint_and_lock_wal().await?;
+        Ok(count)
+    }
+
+    /// Fold the write-ahead log into the chunk files and keep holding the log lock.
+    ///
+    /// No record can be appended until the guard is dropped, so a batch write holding
+    /// it can't be overwritten by an older record left in the log.
+    ///
+    /// Returns the number of log entries applied and the log lock, or `None` if the
+    /// write-ahead log is disabled
+    pub(crate) async fn checkpoint_and_lock_wal(
+        &self,
+    ) -> Result<(usize, Option<LockGuard>), Failure<TableAction>> {
+        if !self.is_wal_enabled() {
+            return Ok((0, None));
+        }
+        let path = self.get_wal_path();
+        let lock = acquire_lock(&path, &self.lock_options)
+            .await
+            .map_err(Failure::wrap(TableAction::Checkpoint))?;
+        let count = self.fold_wal().await?;
+        Ok((count, Some(lock)))
+    }
+
+    /// Fold the write-ahead log into the chunk files while holding the log lock.
+    async fn fold_wal(&self) -> Result<usize, Failure<TableAction>> {
+        let path = self.get_wal_path();
+        let entries = self.read_wal_entries(None).await?;
+        let count = entries.len();
+        for (chunk_hash, chunk_entries) in group_by_chunk::<K, C, Option<T>>(entries) {
+            let _chunk_lock = self
+                .lock_chunk(chunk_hash)
+                .await
+                .map_err(Failure::wrap(TableAction::Checkpoint))?;
+            let mut chunk = match self.find_chunk_path(chunk_hash) {
+                Some(chunk_path) => self.read_chunk(chunk_path).await?,
+                None => BTreeMap::new(),
+            };
+            merge_wal(&mut chunk, chunk_entries);
+            self.write_chunk(chunk_hash, chunk)
+                .await
+                .map_err(Failure::wrap(TableAction::Checkpoint))?;
+        }
+        if path.is_file() {
+            debug!(path = %path.display(), "Removing write-ahead log");
+            remove_file(&path)
+                .await
+                .map_err(Failure::wrap_with_path(TableAction::Checkpoint, &path))?;
+        }
+        *self.wal_tail.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        info!(entries = count, "Checkpointed write-ahead log");
+        Ok(count)
+    }
+}
+
+/// Last valid record of the write-ahead log.
+///
+/// Remembered after each append so the next append only checks this record rather
+/// than reading the whole log to find a partial record.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct WalTail {
+    /// Offset of the record.
+    start: u64,
+    /// Offset after the record.
+    end: u64,
+    /// CRC-32 of the record.
+    checksum: u32,
+}
+
+/// Records parsed from a write-ahead log.
+struct WalScan<const K: usize> {
+    /// Hash and serialized entry of each record.
+    records: Vec<(Hash<K>, Vec<u8>)>,
+    /// Length of the log up to the end of the last valid record.
+    valid: usize,
+    /// Offset and CRC-32 of the last valid record.
+    last: Option<(usize, u32)>,
+}
+
+/// Frame a write-ahead log record.
+///
+/// A record is the length and CRC-32 of the hash and serialized entry, followed by
+/// the hash and serialized entry.
+///
+/// Returns the record and its CRC-32
+fn create_wal_record<const K: usize>(hash: Hash<K>, entry: &[u8]) -> (Vec<u8>, u32) {
+    let mut body = Vec::with_capacity(K + entry.len());
+    body.extend_from_slice(hash.as_bytes());
+    body.extend_from_slice(entry);
+    let length =
+        u32::try_from(body.len()).expect("write-ahead log record should be smaller than 4 GiB");
+    let checksum = crc32fast::hash(&body);
+    let mut record = Vec::with_capacity(FRAME_LENGTH_BYTES + FRAME_CHECKSUM_BYTES + body.len());
+    record.extend_from_slice(&length.to_le_bytes());
+    record.extend_from_slice(&checksum.to_le_bytes());
+    record.extend_from_slice(&body);
+    (record, checksum)
+}
+
+/// Parse the records of a write-ahead log.
+///
+/// Parsing stops at the first partial record or record that fails its checksum.
+fn parse_wal_records<const K: usize>(bytes: &[u8]) -> WalScan<K> {
+    let mut scan = WalScan {
+        records: Vec::new(),
+        valid: 0,
+        last: None,
+    };
+    let mut rest = bytes;
+    while let Some((length, after)) = rest.split_first_chunk::<FRAME_LENGTH_BYTES>() {
+        let length = usize::try_from(u32::from_le_bytes(*length)).expect("u32 should fit in usize");
+        let Some((checksum, after)) = after.split_first_chunk::<FRAME_CHECKSUM_BYTES>() else {
+            break;
+        };
+        let Some((body, after)) = after.split_at_checked(length) else {
+            break;
+        };
+        let checksum = u32::from_le_bytes(*checksum);
+        if crc32fast::hash(body) != checksum {
+            break;
+        }
+        let Some((hash, entry)) = body.split_first_chunk::<K>() else {
+            break;
+        };
+        scan.records.push((Hash::new(*hash), entry.to_vec()));
+        scan.last = Some((scan.valid, checksum));
+        scan.valid += FRAME_LENGTH_BYTES + FRAME_CHECKSUM_BYTES + length;
+        rest = after;
+    }
+    scan
+}
+
+/// Whether the write-ahead log still has `tail` at the same offset.
+///
+/// Fails to match once the log is folded and a new log started, unless the new log
+/// has the same record at the same offset.
+async fn has_wal_record<const K: usize>(
+    file: &mut File,
+    tail: WalTail,
+    len: u64,
+) -> io::Result<bool> {
+    if tail.end > len {
+        return Ok(false);
+    }
+    let length = usize::try_from(tail.end - tail.start).expect("record should fit in memory");
+    let mut bytes = vec![0; length];
+    file.seek(SeekFrom::Start(tail.start)).await?;
+    file.read_exact(&mut bytes).await?;
+    let scan = parse_wal_records::<K>(&bytes);
+    Ok(scan.valid == length && scan.last == Some((0, tail.checksum)))
+}
+
+/// Convert a length in memory to a file offset.
+fn to_offset(length: usize) -> u64 {
+    u64::try_from(length).expect("usize should fit in u64")
+}